@@ -3,19 +3,28 @@ mod types;
 mod modmanager;
 mod ui;
 
-use clap::Parser;
-use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use anyhow::Context;
+use ed25519_dalek::VerifyingKey;
 use eframe::NativeOptions;
-use reqwest::Client;
-use tokio::sync::mpsc::unbounded_channel;
+use tokio::sync::mpsc::{error::TryRecvError, unbounded_channel};
 use tokio::time::sleep;
 
-use crate::types::ModEntry;
-use crate::modmanager::{ModManager, SyncProgress, SyncReport};
-use crate::ui::{theme, ModSyncApp};
+use crate::types::{FileHash, HashAlgorithm, ListDiagnostic, ModEntry, PackInfo, Side};
+use crate::modmanager::cache::{self, Cache};
+use crate::modmanager::config::InstanceConfig;
+use crate::modmanager::net::DownloadPolicy;
+use crate::modmanager::signing;
+use crate::modmanager::transaction;
+use crate::modmanager::{
+    format_bytes, net, ModManager, OfflinePolicy, SyncEvent, SyncOptions, SyncProgress, DEFAULT_CONCURRENCY,
+};
+use crate::ui::{theme, ListNotice, ModSyncApp};
+use crate::ui::diagnostics::DiagnosticsApp;
+use crate::ui::error::ErrorApp;
 use crate::ui::transaction_log::TransactionLogApp;
 
 #[derive(Parser, Debug)]
@@ -25,63 +34,331 @@ use crate::ui::transaction_log::TransactionLogApp;
     about = "ModSync Pre-Launch Tool\n\
            Syncs Minecraft mods before launch.\n\n\
            Mod list format:\n\
-           # Category | ModName | DownloadURL | SHA256\n\
+           # Category | ModName | DownloadURL | Hash | Env | Name\n\
            - Category: REQUIRED or REMOVE\n\
              * REQUIRED: Automatically downloaded; required for the game to run.\n\
              * REMOVE: Deletes the specified mod from the local mods folder.\n\
            \n\
-           - ModName: filename of the mod jar\n\
-           - DownloadURL: URL to download the mod (ignored for REMOVE entries)\n\
-           - SHA256: optional SHA256 hash of the file (ignored for REMOVE entries)"
+           - ModName: filename of the mod jar, optionally prefixed with its folder (resourcepacks/pack.zip)\n\
+           - DownloadURL: URL to download the mod, optionally followed by mirror URLs separated by spaces (ignored for REMOVE entries)\n\
+           - Hash: optional SHA256 hash of the file, or sha1:<hex> / sha512:<hex> (ignored for REMOVE entries)\n\
+           - Env: optional client, server or both (default: both)\n\
+           - Name: optional human-readable name shown in the UI\n\
+           \n\
+           Lines that cannot be parsed are reported with their line number and nothing is synced.\n\
+           TOML manifests (see `convert`) are detected automatically."
 )]
-
 struct Args {
-    /// URL of the remote mod list
-    #[arg(long, conflicts_with = "modsfile")]
-    modsurl: Option<String>,
+    /// URL of the remote mod list; repeat it to add mirrors, which are tried in order
+    #[arg(long, value_name = "URL", conflicts_with = "modsfile")]
+    modsurl: Vec<String>,
 
     /// Local file containing the mod list
     #[arg(long, conflicts_with = "modsurl")]
     modsfile: Option<PathBuf>,
 
     /// Path to the modpack root (default: current dir)
-    #[arg(long)]
+    #[arg(long, global = true)]
     path: Option<PathBuf>,
 
     /// CLI-Mode
     #[arg(long)]
     cli: bool,
 
-    /// Generate SHA256 hash of a file and exit
+    /// Which side this instance is; entries for the other side are skipped or removed
+    #[arg(long, value_enum, default_value_t = Side::Client)]
+    side: Side,
+
+    /// Seconds to wait for a connection or a response before retrying
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    timeout: u64,
+
+    /// Seconds a download may go without receiving data before it is retried
+    #[arg(long, value_name = "SECS", default_value_t = 30)]
+    stall_timeout: u64,
+
+    /// How often a failed download is retried
+    #[arg(long, default_value_t = 3)]
+    retries: u32,
+
+    /// Fail on hash mismatches instead of re-downloading the file
+    #[arg(long)]
+    verify_only: bool,
+
+    /// Remove files from the exclusive folders that are not in the mod list
+    #[arg(long)]
+    exclusive: bool,
+
+    /// Folders --exclusive cleans up, comma-separated
+    #[arg(long, value_name = "DIRS", value_delimiter = ',', default_value = "mods", value_parser = parse_target_dir)]
+    exclusive_dirs: Vec<String>,
+
+    /// Move unlisted files to .modsync/quarantine instead of deleting them (with --exclusive)
+    #[arg(long, requires = "exclusive")]
+    quarantine: bool,
+
+    /// How many mods are checked and downloaded at once (default: 8)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: Option<u32>,
+
+    /// Cap for all downloads together in bytes per second, e.g. 2M
+    #[arg(long, value_name = "RATE", value_parser = cache::parse_size)]
+    max_bandwidth: Option<u64>,
+
+    /// How many downloads may run against the same host at once (default: no limit)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    per_host_connections: Option<u32>,
+
+    /// Also allow downloads from this host, besides modrinth, GitHub, CurseForge and the list's own host
+    /// (repeatable, `*.example.com` includes subdomains, `*` allows any host)
+    #[arg(long, value_name = "HOST")]
+    allow_host: Vec<String>,
+
+    /// Allow downloads over plain HTTP
+    #[arg(long)]
+    allow_http: bool,
+
+    /// Don't use the download cache shared between instances
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Location of the download cache (default: the user cache directory)
+    #[arg(long, value_name = "DIR", global = true)]
+    cache_dir: Option<PathBuf>,
+
+    /// Show what a sync would change without changing anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Hash every file instead of trusting size and modification time recorded after the last check
+    #[arg(long)]
+    full_verify: bool,
+
+    /// How many syncs are kept for `rollback`
+    #[arg(long, value_name = "N", default_value_t = transaction::DEFAULT_KEEP_TRANSACTIONS,
+          value_parser = clap::value_parser!(u32).range(1..))]
+    keep_transactions: u32,
+
+    /// Whether to launch with the last fetched mod list when the list URL can't be reached
+    #[arg(long, value_enum, default_value_t = OfflinePolicy::Allow)]
+    offline_policy: OfflinePolicy,
+
+    /// Only accept mod lists signed with this ed25519 public key (repeatable, base64)
+    #[arg(long, value_name = "KEY", value_parser = signing::parse_public_key)]
+    pubkey: Vec<VerifyingKey>,
+
+    /// Generate the hash of a file for the mod list and exit
     #[arg(long, value_name = "FILE")]
     hash: Option<PathBuf>,
+
+    /// Algorithm --hash uses
+    #[arg(long, value_enum, default_value_t = HashAlgorithm::Sha256, requires = "hash")]
+    hash_algorithm: HashAlgorithm,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert a pipe-separated mod list into a TOML manifest and exit
+    Convert {
+        /// Mod list to convert
+        input: PathBuf,
+
+        /// Where to write the manifest (default: print it)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Pack name for the manifest header
+        #[arg(long)]
+        pack_name: Option<String>,
+
+        /// Pack version for the manifest header
+        #[arg(long)]
+        pack_version: Option<String>,
+
+        /// Oldest ModSync version that can read the pack
+        #[arg(long)]
+        min_modsync_version: Option<String>,
+    },
+
+    /// Undo the last sync, restoring every file it removed or replaced
+    Rollback,
+
+    /// Inspect or clean up the download cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+
+    /// Sign a mod list, so clients that pin the public key can check it hasn't been tampered with
+    Sign {
+        /// Mod list to sign
+        list: PathBuf,
+
+        /// Secret key from `key generate` (repeat it to sign with the old and new key while rotating)
+        #[arg(long, value_name = "FILE", required = true)]
+        key: Vec<PathBuf>,
+
+        /// Write the signature to <LIST>.sig instead of adding it to the list
+        #[arg(long)]
+        detached: bool,
+    },
+
+    /// Create or replace the key mod lists are signed with
+    Key {
+        #[command(subcommand)]
+        action: KeyCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeyCommand {
+    /// Create a new signing key and print its public key
+    Generate {
+        /// Where to write the secret key; the public key goes to <FILE>.pub
+        file: PathBuf,
+    },
+
+    /// Replace a signing key, keeping the old one as <FILE>.old
+    Rotate {
+        /// Secret key to replace
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum CacheCommand {
+    /// Show where the cache is and how big it is
+    Info,
+
+    /// Re-hash all cached files and delete broken ones
+    Verify,
+
+    /// Delete the oldest cached files until the cache fits the given size
+    Gc {
+        /// Size to shrink the cache to, e.g. 500M or 2G
+        #[arg(long, value_parser = cache::parse_size)]
+        max_size: u64,
+    },
 }
 
-enum AppState {
-    Splash,
-    TransactionLog(SyncReport),
-    Exit,
+/// The mod list had errors, which the diagnostics window already showed
+#[derive(Debug)]
+struct ListRejected(usize);
+
+impl std::fmt::Display for ListRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mod list contains {} error(s), no files were changed", self.0)
+    }
 }
 
+impl std::error::Error for ListRejected {}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let args = Args::parse();
 
-    // SHA256 mode
+    // Without --cli there is no console to print to (windows_subsystem), so errors get a window
+    let gui = !args.cli && args.hash.is_none() && args.command.is_none();
+    let result = run(args).await;
+    if gui
+        && let Err(e) = &result
+        && !e.is::<ListRejected>()
+    {
+        show_error_window("ModSync couldn't sync", format!("{:#}", e));
+    }
+    result
+}
+
+async fn run(args: Args) -> anyhow::Result<()> {
+    // Hash mode, printed the way the mod list expects it (`sha512:...`, plain hex for SHA256)
     if let Some(file) = args.hash {
-        let hash = ModManager::sha256_file(&file)?;
-        println!("{}", hash);
+        let hex = ModManager::hash_file(&file, args.hash_algorithm)?;
+        println!("{}", FileHash::new(args.hash_algorithm, &hex));
         return Ok(());
     }
 
+    let cache_dir = args.cache_dir.or_else(Cache::default_dir);
+
     // Determine mods dir
     let mods_dir = args.path.unwrap_or_else(|| std::env::current_dir().unwrap());
+
+    if let Some(command) = args.command {
+        return run_command(command, &mods_dir, cache_dir);
+    }
+
     println!("Mods directory: {}", mods_dir.display());
+    let config = InstanceConfig::load(&mods_dir)?;
+
+    // Keys from the command line and the config are all trusted
+    let mut trusted_keys = config.public_keys()?;
+    trusted_keys.extend(args.pubkey);
 
     // Load mod list
-    let mod_entries: Vec<ModEntry> = ModManager::load_mod_entries(&args.modsfile, &args.modsurl).await?;
+    let timeout = Duration::from_secs(args.timeout);
+    let client = net::build_client(timeout, None).context("Failed to set up HTTP client")?;
+    let loaded = ModManager::load_mod_entries(
+        &args.modsfile,
+        &args.modsurl,
+        &client,
+        timeout,
+        &mods_dir,
+        args.offline_policy,
+        &trusted_keys,
+        args.dry_run,
+    ).await?;
+    let mod_list = loaded.list;
+    let list_notice = match loaded.offline_since {
+        Some(since) => Some(ListNotice::Offline(since)),
+        None if loaded.unchanged => Some(ListNotice::Unchanged),
+        None => None,
+    };
+    if let Some(notice) = &list_notice {
+        println!("{}", notice);
+    }
+    for diagnostic in &mod_list.diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    // Refuse to touch anything if the list is broken
+    if mod_list.has_errors() {
+        let error_count = mod_list.errors().count();
+        if !args.cli {
+            show_diagnostics_window("Mod List Errors", mod_list.diagnostics.clone());
+        }
+        return Err(ListRejected(error_count).into());
+    }
+
+    if let Some(pack) = &mod_list.pack {
+        println!(
+            "Pack: {} {}",
+            pack.name.as_deref().unwrap_or("(unnamed)"),
+            pack.version.as_deref().unwrap_or("")
+        );
+    }
+
+    let warnings: Vec<ListDiagnostic> = mod_list.warnings().cloned().collect();
+    let mod_entries: Vec<ModEntry> = mod_list.entries;
     println!("Loaded {} mods from list", mod_entries.len());
 
+    // Downloads may come from the big mod hosts, the hosts serving the list and whatever is configured
+    let list_hosts = args.modsurl
+        .iter()
+        .filter_map(|url| reqwest::Url::parse(url).ok()?.host_str().map(str::to_string));
+    let download_policy = DownloadPolicy::new(
+        args.allow_http || config.allow_http.unwrap_or(false),
+        net::DEFAULT_ALLOWED_HOSTS
+            .iter()
+            .map(|host| host.to_string())
+            .chain(list_hosts)
+            .chain(config.allowed_hosts.iter().cloned())
+            .chain(args.allow_host),
+    );
+    let download_client = net::build_client(timeout, Some(download_policy.clone()))
+        .context("Failed to set up HTTP client")?;
+
     // Setup progress
     let total = mod_entries.len();
     let progress = Arc::new(SyncProgress::new(total));
@@ -93,15 +370,35 @@ async fn main() -> anyhow::Result<()> {
     let mods_dir_clone = mods_dir.clone();
     let progress_clone = progress.clone();
     let mod_entries_clone = mod_entries.clone();
+    let options = SyncOptions {
+        side: args.side,
+        verify_only: args.verify_only,
+        retries: args.retries,
+        request_timeout: timeout,
+        stall_timeout: Duration::from_secs(args.stall_timeout),
+        cache: cache_dir.filter(|_| !args.no_cache).map(Cache::new),
+        exclusive_dirs: if args.exclusive { args.exclusive_dirs } else { Vec::new() },
+        quarantine: args.quarantine,
+        full_verify: args.full_verify,
+        keep_transactions: args.keep_transactions as usize,
+        dry_run: args.dry_run,
+        offline: matches!(list_notice, Some(ListNotice::Offline(_))),
+        concurrency: args.concurrency.map(|n| n as usize).or(config.concurrency).unwrap_or(DEFAULT_CONCURRENCY),
+        max_bandwidth: args.max_bandwidth.or(config.max_bandwidth()?),
+        per_host_connections: args.per_host_connections.map(|n| n as usize).or(config.per_host_connections),
+        download_policy,
+    };
 
-    tokio::spawn(async move {
-        let _ = ModManager::sync_all_from_entries(
+    // Ends without a Finished event if the sync fails as a whole; the error is taken from here then
+    let sync = tokio::spawn(async move {
+        ModManager::sync_all_from_entries(
             mod_entries_clone,
             mods_dir_clone,
-            Client::new(),
+            download_client,
+            options,
             progress_clone,
             Some(event_tx),
-        ).await;
+        ).await
     });
 
     // Decide if we launch UI or splash mode
@@ -112,7 +409,7 @@ async fn main() -> anyhow::Result<()> {
         // First run the splash window
         let native_options = NativeOptions {
             viewport: egui::ViewportBuilder::default()
-                .with_inner_size([400.0, 260.0])
+                .with_inner_size([480.0, 260.0])
                 .with_resizable(false)
                 .with_decorations(false)
                 .with_title("ModSync"),
@@ -131,20 +428,28 @@ async fn main() -> anyhow::Result<()> {
                     event_rx,
                     5,
                     report_tx,
+                    list_notice,
+                    args.dry_run,
                 )))
             }),
         );
 
+        // The splash closes right away when the sync fails
+        if sync.is_finished() {
+            sync.await.context("Sync task failed")?.context("Sync failed")?;
+        }
+
         // After splash window closes, check if we need to show transaction log
         if let Ok(report) = report_rx.try_recv() {
             // Run transaction log window
+            let title = if report.dry_run { "ModSync - Plan" } else { "ModSync - Transaction Log" };
             let native_options = NativeOptions {
                 viewport: egui::ViewportBuilder::default()
                     .with_inner_size([1500.0, 800.0])
                     .with_min_inner_size([520.0, 320.0])
                     .with_resizable(true)
                     .with_decorations(true)
-                    .with_title("ModSync - Transaction Log"),
+                    .with_title(title),
                 ..Default::default()
             };
 
@@ -154,29 +459,227 @@ async fn main() -> anyhow::Result<()> {
                 Box::new(move |cc| {
                     theme::setup_fonts(&cc.egui_ctx);
                     theme::setup_dark_theme(&cc.egui_ctx);
-                    Ok(Box::new(TransactionLogApp::new(report)))
+                    Ok(Box::new(TransactionLogApp::new(report, warnings, mods_dir)))
                 }),
             );
         }
     } else {
         // CLI mode
+        let mut event_rx = event_rx;
+        let mut finished = false;
         loop {
+            // The sync is over once it reports its result (or stops without one)
+            loop {
+                match event_rx.try_recv() {
+                    Ok(event) => {
+                        finished |= matches!(event, SyncEvent::Finished(_));
+                        // A dry run prints its plan at the end instead
+                        if !args.dry_run {
+                            print_event(&event);
+                        }
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                    Err(TryRecvError::Empty) => break,
+                }
+            }
+
             let processed = progress.processed();
-            let total = progress.total;
+            let total = progress.total();
 
             // Print live progress
-            println!("Progress: {}/{}", processed, total);
+            match progress.stats().transfer_summary() {
+                Some(transfer) => println!("Progress: {}/{} ({})", processed, total, transfer),
+                None => println!("Progress: {}/{}", processed, total),
+            }
 
-            if(processed == total) {
-                println!("Done!");
-                sleep(Duration::from_millis(500)).await;
+            if finished {
                 break;
             }
 
             sleep(Duration::from_millis(250)).await;
         }
+
+        sync.await.context("Sync task failed")?.context("Sync failed")?;
+        println!("Done!");
+        sleep(Duration::from_millis(500)).await;
+    }
+
+    if args.dry_run {
+        println!("Dry run finished, nothing was changed.");
+    } else {
+        println!("Exiting ModSync. Minecraft launcher should start now.");
+    }
+    Ok(())
+}
+
+/// Runs a subcommand instead of syncing
+fn run_command(command: Command, mods_dir: &Path, cache_dir: Option<PathBuf>) -> anyhow::Result<()> {
+    match command {
+        Command::Convert { input, output, pack_name, pack_version, min_modsync_version } => {
+            let text = std::fs::read_to_string(&input)
+                .with_context(|| format!("Failed to read {}", input.display()))?;
+            let list = types::parse_mod_list(&text);
+            for diagnostic in &list.diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            if list.has_errors() {
+                anyhow::bail!("Fix the errors in {} before converting it", input.display());
+            }
+
+            let pack = PackInfo {
+                name: pack_name,
+                version: pack_version,
+                min_modsync_version,
+            };
+            let manifest = types::to_manifest(&list, pack)?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, manifest)
+                        .with_context(|| format!("Failed to write {}", path.display()))?;
+                    println!("Converted {} entries to {}", list.entries.len(), path.display());
+                }
+                None => print!("{}", manifest),
+            }
+        }
+        Command::Rollback => {
+            let summary = transaction::rollback(mods_dir, None)?;
+            println!(
+                "Rolled back sync {}: {} file(s) restored, {} file(s) deleted",
+                summary.id, summary.restored, summary.deleted
+            );
+        }
+        Command::Cache { action } => {
+            let cache = Cache::new(cache_dir.context("No cache directory on this system, pass --cache-dir")?);
+            match action {
+                CacheCommand::Info => {
+                    let info = cache.info()?;
+                    println!("Cache directory: {}", cache.root().display());
+                    println!("{} file(s), {}", info.objects, format_bytes(info.bytes));
+                }
+                CacheCommand::Verify => {
+                    let removed = cache.verify()?;
+                    for name in &removed {
+                        println!("Removed broken file {}", name);
+                    }
+                    println!("{} broken file(s) removed", removed.len());
+                }
+                CacheCommand::Gc { max_size } => {
+                    let (removed, freed) = cache.gc(max_size)?;
+                    println!("Removed {} file(s), freed {}", removed, format_bytes(freed));
+                }
+            }
+        }
+        Command::Sign { list, key, detached } => {
+            let keys = key.iter().map(|path| signing::load_signing_key(path)).collect::<anyhow::Result<Vec<_>>>()?;
+            let text = std::fs::read_to_string(&list)
+                .with_context(|| format!("Failed to read {}", list.display()))?;
+            if detached {
+                let path = signing::with_suffix(&list, signing::SIGNATURE_EXTENSION);
+                std::fs::write(&path, signing::signature_lines(&text, &keys))
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("Wrote signature to {}, upload it next to the list", path.display());
+            } else {
+                std::fs::write(&list, signing::sign_embedded(&text, &keys))
+                    .with_context(|| format!("Failed to write {}", list.display()))?;
+                println!("Signed {}", list.display());
+            }
+        }
+        Command::Key { action } => match action {
+            KeyCommand::Generate { file } => {
+                let key = signing::generate_key(&file)?;
+                println!("Secret key written to {}, keep it private", file.display());
+                println!("Public key: {}", signing::public_key_text(&key.verifying_key()));
+            }
+            KeyCommand::Rotate { file } => {
+                let (old, new) = signing::rotate_key(&file)?;
+                println!("Old public key: {}", signing::public_key_text(&old.verifying_key()));
+                println!("New public key: {}", signing::public_key_text(&new.verifying_key()));
+                println!(
+                    "Sign with both keys (--key {} --key {}) until every player pins the new one",
+                    file.display(),
+                    signing::with_suffix(&file, "old").display()
+                );
+            }
+        },
     }
 
-    println!("Exiting ModSync. Minecraft launcher should start now.");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Only the known instance folders may be cleaned up by --exclusive
+fn parse_target_dir(value: &str) -> Result<String, String> {
+    let dir = value.trim().trim_end_matches('/');
+    if types::TARGET_DIRS.contains(&dir) {
+        Ok(dir.to_string())
+    } else {
+        Err(format!("unknown folder `{}` (expected one of: {})", value, types::TARGET_DIRS.join(", ")))
+    }
+}
+
+/// Prints a single sync event in CLI mode
+fn print_event(event: &SyncEvent) {
+    match event {
+        SyncEvent::Downloaded { filename, name } => println!("+ {} ({})", name, filename),
+        SyncEvent::Updated { filename, name } => println!("* {} ({})", name, filename),
+        SyncEvent::Unchanged { filename, name } => println!("~ {} ({})", name, filename),
+        SyncEvent::Removed { filename, name } => println!("- {} ({})", name, filename),
+        SyncEvent::Skipped { filename, name } => println!("/ {} ({})", name, filename),
+        SyncEvent::Failed { filename, name, error } => println!("! {} ({}): {}", name, filename, error),
+        SyncEvent::Retrying { filename, name, attempt, max_attempts, delay, error } => println!(
+            "? {} ({}): {}, retrying in {}s (attempt {}/{})",
+            name, filename, error, delay.as_secs(), attempt, max_attempts
+        ),
+        SyncEvent::Finished(_) => {}
+    }
+}
+
+/// Blocks until the user closes the diagnostics window
+fn show_diagnostics_window(title: &str, diagnostics: Vec<ListDiagnostic>) {
+    let title = title.to_string();
+    let native_options = NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([900.0, 500.0])
+            .with_min_inner_size([520.0, 320.0])
+            .with_resizable(true)
+            .with_decorations(true)
+            .with_title(format!("ModSync - {}", title)),
+        ..Default::default()
+    };
+
+    let _ = eframe::run_native(
+        "ModSync - Diagnostics",
+        native_options,
+        Box::new(move |cc| {
+            theme::setup_fonts(&cc.egui_ctx);
+            theme::setup_dark_theme(&cc.egui_ctx);
+            Ok(Box::new(DiagnosticsApp::new(title, diagnostics)))
+        }),
+    );
+}
+
+/// Blocks until the user closes the error window
+fn show_error_window(title: &str, message: String) {
+    let title = title.to_string();
+    let native_options = NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([640.0, 320.0])
+            .with_min_inner_size([420.0, 240.0])
+            .with_resizable(true)
+            .with_decorations(true)
+            .with_title("ModSync - Error"),
+        ..Default::default()
+    };
+
+    let _ = eframe::run_native(
+        "ModSync - Error",
+        native_options,
+        Box::new(move |cc| {
+            theme::setup_fonts(&cc.egui_ctx);
+            theme::setup_dark_theme(&cc.egui_ctx);
+            Ok(Box::new(ErrorApp::new(title, message)))
+        }),
+    );
+}
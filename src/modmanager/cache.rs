@@ -0,0 +1,204 @@
+use anyhow::{Context, Result};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::modmanager::{part_path, ModManager};
+use crate::types::{FileHash, HashAlgorithm};
+
+/// Download cache shared by every instance of the current user.
+/// Files are stored under `objects/<sha256>` (`objects/<algorithm>-<hex>` for other algorithms),
+/// so identical jars are only downloaded once.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: PathBuf,
+}
+
+/// Number and total size of cached files
+pub struct CacheInfo {
+    pub objects: usize,
+    pub bytes: u64,
+}
+
+/// A cached file as seen by `cache gc`
+struct CachedObject {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl Cache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// `<user cache dir>/modsync`, None on systems without a cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("modsync"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn objects_dir(&self) -> PathBuf {
+        self.root.join("objects")
+    }
+
+    fn object_path(&self, hash: &FileHash) -> PathBuf {
+        self.objects_dir().join(object_name(hash))
+    }
+
+    /// Installs a cached file at `target`. Returns false if the file isn't cached.
+    /// The copy is verified before it replaces anything; broken cache entries are dropped.
+    pub fn install(&self, hash: &FileHash, target: &Path, link: bool) -> Result<bool> {
+        let object = self.object_path(hash);
+        if !object.is_file() {
+            return Ok(false);
+        }
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context("Failed to create target folder")?;
+        }
+        let part = part_path(target);
+        let _ = fs::remove_file(&part);
+        if !(link && fs::hard_link(&object, &part).is_ok()) {
+            fs::copy(&object, &part).context("Failed to copy file from the download cache")?;
+        }
+
+        let actual = ModManager::hash_file(&part, hash.algorithm)?;
+        if !hash.matches(&actual) {
+            let _ = fs::remove_file(&part);
+            let _ = fs::remove_file(&object);
+            return Ok(false);
+        }
+
+        fs::rename(&part, target).context("Failed to move cached file into place")?;
+        Ok(true)
+    }
+
+    /// Adds a verified file to the cache (no-op if it is already there)
+    pub fn store(&self, hash: &FileHash, source: &Path, link: bool) -> Result<()> {
+        let object = self.object_path(hash);
+        if object.exists() {
+            return Ok(());
+        }
+
+        let objects_dir = self.objects_dir();
+        fs::create_dir_all(&objects_dir).context("Failed to create download cache")?;
+
+        // Write under a temporary name, so other instances never see a half-copied object
+        let tmp = objects_dir.join(format!("{}.{}.tmp", object_name(hash), std::process::id()));
+        let _ = fs::remove_file(&tmp);
+        if !(link && fs::hard_link(source, &tmp).is_ok()) {
+            fs::copy(source, &tmp).context("Failed to copy file into the download cache")?;
+        }
+        fs::rename(&tmp, &object).context("Failed to add file to the download cache")?;
+        Ok(())
+    }
+
+    pub fn info(&self) -> Result<CacheInfo> {
+        let objects = self.objects()?;
+        Ok(CacheInfo {
+            objects: objects.len(),
+            bytes: objects.iter().map(|o| o.size).sum(),
+        })
+    }
+
+    /// Re-hashes every cached file and deletes the ones that don't match their name.
+    /// Returns the names of the deleted files.
+    pub fn verify(&self) -> Result<Vec<String>> {
+        let mut removed = Vec::new();
+        for object in self.objects()? {
+            let name = object.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            let valid = parse_object_name(&name).is_some_and(|hash| {
+                ModManager::hash_file(&object.path, hash.algorithm).is_ok_and(|actual| hash.matches(&actual))
+            });
+            if !valid {
+                fs::remove_file(&object.path)
+                    .context(format!("Failed to remove broken cache entry {}", name))?;
+                removed.push(name);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Deletes the oldest files until the cache is at most `max_size` bytes.
+    /// Returns how many files were deleted and how many bytes that freed.
+    pub fn gc(&self, max_size: u64) -> Result<(usize, u64)> {
+        let mut objects = self.objects()?;
+        objects.sort_by_key(|o| o.modified);
+
+        let mut total: u64 = objects.iter().map(|o| o.size).sum();
+        let (mut removed, mut freed) = (0, 0);
+        for object in objects {
+            if total <= max_size {
+                break;
+            }
+            fs::remove_file(&object.path)
+                .context(format!("Failed to remove {}", object.path.display()))?;
+            total -= object.size;
+            freed += object.size;
+            removed += 1;
+        }
+        Ok((removed, freed))
+    }
+
+    /// Every complete object; temporary files of running syncs are left out
+    fn objects(&self) -> Result<Vec<CachedObject>> {
+        let dir = match fs::read_dir(self.objects_dir()) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).context("Failed to read download cache"),
+        };
+
+        let mut objects = Vec::new();
+        for entry in dir {
+            let entry = entry.context("Failed to read download cache")?;
+            let metadata = entry.metadata().context("Failed to read download cache")?;
+            if !metadata.is_file() || entry.path().extension().is_some_and(|e| e == "tmp") {
+                continue;
+            }
+            objects.push(CachedObject {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            });
+        }
+        Ok(objects)
+    }
+}
+
+/// Parses sizes like `500M`, `2G` or `1048576` (bytes), for `cache gc --max-size`
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse().map_err(|_| format!("invalid size `{}`", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().trim_end_matches('B') {
+        "" => 1,
+        "K" | "KI" => 1024,
+        "M" | "MI" => 1024 * 1024,
+        "G" | "GI" => 1024 * 1024 * 1024,
+        "T" | "TI" => 1024 * 1024 * 1024 * 1024,
+        _ => return Err(format!("unknown size unit `{}` (expected K, M, G or T)", unit)),
+    };
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// File name of a cached object. SHA256 objects keep the plain hex name they always had.
+fn object_name(hash: &FileHash) -> String {
+    match hash.algorithm {
+        HashAlgorithm::Sha256 => hash.hex.clone(),
+        algorithm => format!("{}-{}", algorithm.as_str(), hash.hex),
+    }
+}
+
+fn parse_object_name(name: &str) -> Option<FileHash> {
+    let text = match name.split_once('-') {
+        Some((algorithm, hex)) => format!("{}:{}", algorithm, hex),
+        None => name.to_string(),
+    };
+    FileHash::parse(&text).ok()
+}
@@ -0,0 +1,72 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use std::fs;
+use std::path::Path;
+
+use ed25519_dalek::VerifyingKey;
+
+use crate::modmanager::cache::parse_size;
+use crate::modmanager::signing;
+use crate::modmanager::STATE_DIR;
+
+/// Per-instance settings, inside STATE_DIR. Command line options take precedence.
+pub const CONFIG_FILE: &str = "config.toml";
+
+/// Contents of `.modsync/config.toml`:
+///
+/// ```toml
+/// concurrency = 4
+/// max_bandwidth = "2M"       # bytes per second
+/// per_host_connections = 2
+/// allowed_hosts = ["mods.example.com", "*.example.org"]   # besides the defaults, "*" for any
+/// allow_http = false
+/// public_keys = ["<base64>"]   # only lists signed by one of these are accepted
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstanceConfig {
+    pub concurrency: Option<usize>,
+    pub max_bandwidth: Option<String>,
+    pub per_host_connections: Option<usize>,
+    #[serde(default)]
+    pub allowed_hosts: Vec<String>,
+    pub allow_http: Option<bool>,
+    #[serde(default)]
+    pub public_keys: Vec<String>,
+}
+
+impl InstanceConfig {
+    /// Reads the config; a missing file means defaults everywhere
+    pub fn load(mods_dir: &Path) -> Result<Self> {
+        let path = mods_dir.join(STATE_DIR).join(CONFIG_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+
+        let config: Self = toml::from_str(&text).context(format!("Invalid {}", path.display()))?;
+        if config.concurrency == Some(0) || config.per_host_connections == Some(0) {
+            anyhow::bail!("Invalid {}: connection limits must be at least 1", path.display());
+        }
+        Ok(config)
+    }
+
+    /// `public_keys`, parsed
+    pub fn public_keys(&self) -> Result<Vec<VerifyingKey>> {
+        self.public_keys
+            .iter()
+            .map(|key| signing::parse_public_key(key).context(format!("Invalid public_keys in {}", CONFIG_FILE)))
+            .collect()
+    }
+
+    /// `max_bandwidth` in bytes per second
+    pub fn max_bandwidth(&self) -> Result<Option<u64>> {
+        self.max_bandwidth
+            .as_deref()
+            .map(parse_size)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("Invalid max_bandwidth in {}: {}", CONFIG_FILE, e))
+    }
+}
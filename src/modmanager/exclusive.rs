@@ -0,0 +1,195 @@
+use anyhow::{Context, Result};
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::modmanager::STATE_DIR;
+use crate::types::ModEntry;
+
+/// Files in exclusive folders that are never touched, one name or path per line (`*` matches anything)
+pub const ALLOWLIST_FILE: &str = "allowlist.txt";
+
+/// Extras are moved here instead of being deleted when --quarantine is set
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// User-maintained list of personal files that exclusive mode leaves alone
+pub struct Allowlist {
+    patterns: Vec<String>,
+}
+
+impl Allowlist {
+    /// Reads `.modsync/allowlist.txt`; a missing file means an empty allowlist
+    pub fn load(mods_dir: &Path) -> Result<Self> {
+        let path = mods_dir.join(STATE_DIR).join(ALLOWLIST_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).context(format!("Failed to read {}", path.display())),
+        };
+
+        let patterns = text
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.replace('\\', "/").to_lowercase())
+            .collect();
+        Ok(Self { patterns })
+    }
+
+    /// Matches patterns against both the file name and the path relative to the instance
+    pub fn contains(&self, relative_path: &str) -> bool {
+        let path = relative_path.to_lowercase();
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        self.patterns.iter().any(|p| wildcard_match(p, name) || wildcard_match(p, &path))
+    }
+}
+
+/// Files directly inside the exclusive folders that are neither listed nor allowlisted.
+/// Returned as REMOVE entries, so they are reported like any other removal.
+pub fn find_extras(entries: &[ModEntry], mods_dir: &Path, dirs: &[String], allowlist: &Allowlist) -> Result<Vec<ModEntry>> {
+    // Compared case-insensitively, Windows would treat them as the same file
+    let listed: HashSet<String> = entries.iter().map(|e| e.relative_path().to_lowercase()).collect();
+
+    let mut extras = Vec::new();
+    for dir in dirs {
+        let read_dir = match fs::read_dir(mods_dir.join(dir)) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).context(format!("Failed to read {}", dir)),
+        };
+
+        for file in read_dir {
+            let file = file.context(format!("Failed to read {}", dir))?;
+            if !file.file_type().is_ok_and(|t| t.is_file()) {
+                continue;
+            }
+            let name = file.file_name().to_string_lossy().to_string();
+            // Unfinished downloads are resumed by the next sync
+            if name.ends_with(".part") || name.ends_with(".part.validator") {
+                continue;
+            }
+
+            let relative_path = format!("{}/{}", dir, name);
+            if listed.contains(&relative_path.to_lowercase()) || allowlist.contains(&relative_path) {
+                continue;
+            }
+            extras.push(ModEntry::unlisted(relative_path));
+        }
+    }
+
+    extras.sort_by(|a, b| a.filename.cmp(&b.filename));
+    Ok(extras)
+}
+
+/// Quarantine folder for this run: `.modsync/quarantine/<unix time>`
+pub fn quarantine_dir(mods_dir: &Path) -> PathBuf {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    mods_dir.join(STATE_DIR).join(QUARANTINE_DIR).join(timestamp.to_string())
+}
+
+/// Moves a file into the quarantine folder, keeping its path relative to the instance.
+/// Returns where the file went.
+pub fn quarantine(entry: &ModEntry, mods_dir: &Path, quarantine_dir: &Path) -> Result<PathBuf> {
+    let target = quarantine_dir.join(entry.relative_path());
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).context("Failed to create quarantine folder")?;
+    }
+    fs::rename(entry.local_path(mods_dir), &target)
+        .context(format!("Failed to move {} to quarantine", entry.filename))?;
+    Ok(target)
+}
+
+/// Glob matching where `*` stands for any number of characters
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+    let Some(mut remaining) = text.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or_default();
+    for part in parts {
+        match remaining.find(part) {
+            Some(i) => remaining = &remaining[i + part.len()..],
+            None => return false,
+        }
+    }
+    remaining.len() >= last.len() && remaining.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards_match_any_characters() {
+        let cases = [
+            ("sodium.jar", "sodium.jar", true),
+            ("sodium.jar", "sodium.jar.disabled", false),
+            ("*", "anything.jar", true),
+            ("*", "", true),
+            ("*.jar", "a.jar", true),
+            ("*.jar", ".jar", true),
+            ("*.jar", "a.jar.disabled", false),
+            ("journeymap*", "journeymap-5.9.jar", true),
+            ("journeymap*", "xjourneymap.jar", false),
+            ("mods/*-client.jar", "mods/map-client.jar", true),
+            ("mods/*-client.jar", "config/map-client.jar", false),
+            ("a*b*c", "abc", true),
+            ("a*b*c", "axxbyyc", true),
+            ("a*b*c", "acb", false),
+            ("a*a", "a", false),
+            ("*ab*ab", "xabab", true),
+            ("**", "x", true),
+        ];
+        for (pattern, text, matches) in cases {
+            assert_eq!(wildcard_match(pattern, text), matches, "`{}` on `{}`", pattern, text);
+        }
+    }
+
+    fn allowlist(patterns: &[&str]) -> Allowlist {
+        Allowlist { patterns: patterns.iter().map(|p| p.to_string()).collect() }
+    }
+
+    #[test]
+    fn allowlist_matches_names_and_paths() {
+        let list = allowlist(&["optifine*.jar", "shaderpacks/my-*"]);
+        assert!(list.contains("mods/OptiFine_1.20.jar"));
+        assert!(list.contains("shaderpacks/my-shader.zip"));
+        assert!(!list.contains("resourcepacks/my-pack.zip"));
+        assert!(!list.contains("mods/sodium.jar"));
+    }
+
+    #[test]
+    fn allowlist_file_is_optional() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!Allowlist::load(dir.path()).unwrap().contains("mods/a.jar"));
+
+        fs::create_dir_all(dir.path().join(STATE_DIR)).unwrap();
+        fs::write(dir.path().join(STATE_DIR).join(ALLOWLIST_FILE), "# mine\n\n  Personal.jar \n").unwrap();
+        assert!(Allowlist::load(dir.path()).unwrap().contains("mods/personal.jar"));
+    }
+
+    #[test]
+    fn finds_unlisted_files_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods = dir.path().join("mods");
+        fs::create_dir_all(mods.join("sub")).unwrap();
+        for name in ["listed.jar", "Other.jar", "mine.jar", "new.jar.part", "new.jar.part.validator", "sub/deep.jar"] {
+            fs::write(mods.join(name), "x").unwrap();
+        }
+
+        let entries = vec![ModEntry::unlisted("LISTED.jar".to_string())];
+        let extras = find_extras(&entries, dir.path(), &["mods".to_string(), "shaderpacks".to_string()], &allowlist(&["mine.jar"]))
+            .unwrap();
+        let names: Vec<&str> = extras.iter().map(|e| e.filename.as_str()).collect();
+        assert_eq!(names, ["mods/Other.jar"]);
+        assert!(extras[0].is_remove());
+    }
+}
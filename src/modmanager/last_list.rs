@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::modmanager::STATE_DIR;
+
+/// Copy of the last mod list fetched without errors, inside STATE_DIR
+pub const LAST_LIST_FILE: &str = "last-list.json";
+
+/// The last good mod list of an instance, used when the list URL can't be reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastList {
+    /// The list URL that served it
+    pub url: String,
+    /// Seconds since the Unix epoch
+    pub fetched_at: u64,
+    pub text: String,
+    /// Validators the server sent with the list, for conditional requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Detached signature served with the list, checked again before an offline launch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl LastList {
+    pub fn new(
+        url: &str,
+        text: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        signature: Option<String>,
+    ) -> Self {
+        Self { url: url.to_string(), fetched_at: now(), text, etag, last_modified, signature }
+    }
+
+    /// The server confirmed the list is unchanged: it counts as fetched now
+    pub fn revalidated(mut self, etag: Option<String>, last_modified: Option<String>, signature: Option<String>) -> Self {
+        self.fetched_at = now();
+        self.etag = etag.or(self.etag);
+        self.last_modified = last_modified.or(self.last_modified);
+        self.signature = signature.or(self.signature);
+        self
+    }
+
+    /// Loads the saved list if it was fetched from one of `urls`; a list of another pack is no fallback
+    pub fn load(mods_dir: &Path, urls: &[String]) -> Option<Self> {
+        let text = fs::read_to_string(mods_dir.join(STATE_DIR).join(LAST_LIST_FILE)).ok()?;
+        let last: Self = serde_json::from_str(&text).ok()?;
+        urls.contains(&last.url).then_some(last)
+    }
+
+    pub fn save(&self, mods_dir: &Path) -> Result<()> {
+        let dir = mods_dir.join(STATE_DIR);
+        fs::create_dir_all(&dir).context("Failed to create .modsync folder")?;
+        // Written next to the old copy and moved over it, so a crash never leaves a broken fallback
+        let path = dir.join(LAST_LIST_FILE);
+        let tmp = dir.join(format!("{}.tmp", LAST_LIST_FILE));
+        let json = serde_json::to_string(self).context("Failed to serialize mod list")?;
+        fs::write(&tmp, json).context("Failed to save mod list for offline use")?;
+        fs::rename(&tmp, &path).context("Failed to save mod list for offline use")
+    }
+
+    /// When the list was fetched, as an HTTP date (`Sun, 06 Nov 2025 08:49:37 GMT`)
+    pub fn fetched_at_text(&self) -> String {
+        httpdate::fmt_http_date(UNIX_EPOCH + Duration::from_secs(self.fetched_at))
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -1,76 +1,289 @@
 use anyhow::{Context, Result};
-use crate::types::ModEntry;
+use crate::types::{FileHash, HashAlgorithm, Hasher, ModEntry, ModList, Side, DEFAULT_TARGET_DIR};
+use crate::modmanager::cache::Cache;
+use crate::modmanager::exclusive::{self, Allowlist};
+use crate::modmanager::last_list::LastList;
+use crate::modmanager::state::InstanceState;
+use crate::modmanager::transaction::Transaction;
+use crate::modmanager::signing;
+use crate::modmanager::net::{self, DownloadPolicy, HostLimiter, ListResponse, RateLimiter, Transient};
 
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::{
     Arc,
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicU64, AtomicUsize, Ordering},
 };
+use std::time::{Duration, Instant};
 
-use sha2::{Digest, Sha256};
-use reqwest::Client;
+use reqwest::header::{IF_RANGE, RANGE};
+use ed25519_dalek::VerifyingKey;
+use reqwest::{Client, StatusCode};
+use tokio::io::AsyncWriteExt;
 use tokio::sync::mpsc::UnboundedSender;
 use futures::{stream, StreamExt};
 
+/// Folder in the instance root where ModSync keeps its own files
+pub const STATE_DIR: &str = ".modsync";
+
+/// Read size used when hashing files
+const HASH_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// How many entries are synced at once unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// How often the download speed estimate is refreshed
+const SPEED_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Final report of a sync operation
 #[derive(Debug, Clone)]
 pub struct SyncReport {
     pub downloaded: Vec<ModEntry>,
+    pub updated: Vec<ModEntry>,
     pub unchanged: Vec<ModEntry>,
     pub removed: Vec<ModEntry>,
+    pub skipped: Vec<ModEntry>,
     pub failed: Vec<(ModEntry, String)>,
+    /// Id of the transaction that can undo this sync, None if nothing changed
+    pub transaction: Option<String>,
+    /// The report is a plan from --dry-run; `failed` holds the entries that couldn't be planned
+    pub dry_run: bool,
+    /// URL each downloaded file came from, by filename (files installed from the cache have none)
+    pub sources: HashMap<String, String>,
+    /// Remarks on planned changes by filename, e.g. that an update replaces a file changed locally
+    pub notes: HashMap<String, String>,
+    /// Why exclusive mode left a folder (or all of them) alone
+    pub exclusive_errors: Vec<String>,
+}
+
+/// What to do when the mod list URL can't be reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OfflinePolicy {
+    /// Sync against the last list fetched for this instance, keeping what is installed
+    #[default]
+    Allow,
+    /// Fail, so the game isn't launched with a possibly outdated pack
+    Block,
+}
+
+/// A parsed mod list and where it came from
+pub struct LoadedList {
+    pub list: ModList,
+    /// Set when the list URL couldn't be reached: when the saved copy that was used instead was fetched
+    pub offline_since: Option<String>,
+    /// The server confirmed the list is the same as on the last launch (HTTP 304)
+    pub unchanged: bool,
+}
+
+/// Settings that change how entries are synced
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    /// Entries whose env doesn't include this side are skipped (and removed if present)
+    pub side: Side,
+    /// Fail on hash mismatches instead of re-downloading the file
+    pub verify_only: bool,
+    /// How often a download is retried after a transient failure
+    pub retries: u32,
+    /// How long to wait for the response headers of a download
+    pub request_timeout: Duration,
+    /// How long a download may go without receiving data
+    pub stall_timeout: Duration,
+    /// Shared download cache, None if disabled with --no-cache
+    pub cache: Option<Cache>,
+    /// Folders in which unlisted files are removed (exclusive mode); empty to leave them alone
+    pub exclusive_dirs: Vec<String>,
+    /// Move unlisted files to `.modsync/quarantine` instead of deleting them
+    pub quarantine: bool,
+    /// Hash every file, even if size and mtime match what was recorded after the last verification
+    pub full_verify: bool,
+    /// How many syncs can be rolled back
+    pub keep_transactions: usize,
+    /// Only work out what would change; nothing is written, deleted or downloaded
+    pub dry_run: bool,
+    /// The list server couldn't be reached: nothing is downloaded, installed files are kept
+    pub offline: bool,
+    /// How many entries are checked and downloaded at once
+    pub concurrency: usize,
+    /// Cap for all downloads together, in bytes per second
+    pub max_bandwidth: Option<u64>,
+    /// How many downloads may run against the same host at once
+    pub per_host_connections: Option<usize>,
+    /// Schemes and hosts downloads may use; the client must enforce it on redirects too
+    pub download_policy: DownloadPolicy,
 }
 
 /// Shared progress state (UI-readable at any time)
 #[derive(Debug)]
 pub struct SyncProgress {
-    pub total: usize,
+    total: AtomicUsize,
     pub processed: AtomicUsize,
     pub downloaded: AtomicUsize,
+    pub updated: AtomicUsize,
     pub unchanged: AtomicUsize,
     pub removed: AtomicUsize,
+    pub skipped: AtomicUsize,
     pub failed: AtomicUsize,
 
     // Keep the last processed mod for UI
     last_mod: parking_lot::Mutex<Option<String>>,
+
+    // Byte counts of running downloads by filename, and of finished ones
+    downloads: parking_lot::Mutex<HashMap<String, ByteProgress>>,
+    // Downloads that haven't started yet, with their size if the list gives it
+    queued: parking_lot::Mutex<HashMap<String, Option<u64>>>,
+    completed_bytes: AtomicU64,
+    // Bytes received over the network, including failed attempts (for the speed estimate)
+    transferred: AtomicU64,
+    speed: parking_lot::Mutex<SpeedMeter>,
+}
+
+/// Bytes of a single download; `received` includes data resumed from a .part file
+#[derive(Debug, Clone, Copy)]
+struct ByteProgress {
+    received: u64,
+    expected: Option<u64>,
+}
+
+/// Download speed smoothed over SPEED_SAMPLE_INTERVAL
+#[derive(Debug)]
+struct SpeedMeter {
+    sampled_at: Instant,
+    sampled_bytes: u64,
+    bytes_per_sec: f64,
 }
 
 impl SyncProgress {
     pub fn new(total: usize) -> Self {
         Self {
-            total,
+            total: AtomicUsize::new(total),
             processed: AtomicUsize::new(0),
             downloaded: AtomicUsize::new(0),
+            updated: AtomicUsize::new(0),
             unchanged: AtomicUsize::new(0),
             removed: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
             failed: AtomicUsize::new(0),
             last_mod: parking_lot::Mutex::new(None),
+            downloads: parking_lot::Mutex::new(HashMap::new()),
+            queued: parking_lot::Mutex::new(HashMap::new()),
+            completed_bytes: AtomicU64::new(0),
+            transferred: AtomicU64::new(0),
+            speed: parking_lot::Mutex::new(SpeedMeter {
+                sampled_at: Instant::now(),
+                sampled_bytes: 0,
+                bytes_per_sec: 0.0,
+            }),
         }
     }
 
+    /// Number of entries to process, including unlisted files found in exclusive mode
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
     /// Total number of mods processed so far
     pub fn processed(&self) -> usize {
         self.downloaded.load(Ordering::Relaxed)
+            + self.updated.load(Ordering::Relaxed)
             + self.unchanged.load(Ordering::Relaxed)
             + self.removed.load(Ordering::Relaxed)
+            + self.skipped.load(Ordering::Relaxed)
             + self.failed.load(Ordering::Relaxed)
     }
 
     /// Get current counts for UI display
     pub fn stats(&self) -> SyncStats {
+        let completed_bytes = self.completed_bytes.load(Ordering::Relaxed);
+        let downloads: Vec<ByteProgress> = self.downloads.lock().values().copied().collect();
+        let queued: Vec<Option<u64>> = self.queued.lock().values().copied().collect();
+
+        let bytes_received = completed_bytes + downloads.iter().map(|d| d.received).sum::<u64>();
+        let size_known = downloads.iter().all(|d| d.expected.is_some()) && queued.iter().all(Option::is_some);
+        let bytes_expected = completed_bytes
+            + downloads.iter().map(|d| d.expected.unwrap_or(d.received).max(d.received)).sum::<u64>()
+            + queued.iter().flatten().sum::<u64>();
+        let in_flight = downloads
+            .iter()
+            .filter_map(|d| d.expected.filter(|&e| e > 0).map(|e| (d.received as f64 / e as f64).min(1.0)))
+            .sum::<f64>() as f32;
+
+        let bytes_per_sec = self.sample_speed();
+        let eta = (size_known && !downloads.is_empty() && bytes_per_sec > 0.0)
+            .then(|| Duration::from_secs_f64((bytes_expected - bytes_received) as f64 / bytes_per_sec));
+
         SyncStats {
             downloaded: self.downloaded.load(Ordering::Relaxed),
+            updated: self.updated.load(Ordering::Relaxed),
             unchanged: self.unchanged.load(Ordering::Relaxed),
             removed: self.removed.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
             failed: self.failed.load(Ordering::Relaxed),
+            active_downloads: downloads.len(),
+            in_flight,
+            bytes_received,
+            bytes_expected,
+            bytes_per_sec,
+            eta,
+        }
+    }
+
+    /// Refreshes the speed estimate once per SPEED_SAMPLE_INTERVAL and returns it
+    fn sample_speed(&self) -> f64 {
+        let transferred = self.transferred.load(Ordering::Relaxed);
+        let mut speed = self.speed.lock();
+        let elapsed = speed.sampled_at.elapsed();
+        if elapsed >= SPEED_SAMPLE_INTERVAL {
+            let current = (transferred - speed.sampled_bytes) as f64 / elapsed.as_secs_f64();
+            // Smooth out bursty chunk delivery
+            speed.bytes_per_sec = if speed.bytes_per_sec == 0.0 {
+                current
+            } else {
+                speed.bytes_per_sec * 0.6 + current * 0.4
+            };
+            speed.sampled_at = Instant::now();
+            speed.sampled_bytes = transferred;
+        }
+        speed.bytes_per_sec
+    }
+
+    /// Registers a file that is going to be downloaded, so its size counts before the download starts
+    fn queue_download(&self, filename: &str, size: Option<u64>) {
+        self.queued.lock().insert(filename.to_string(), size);
+    }
+
+    /// Drops a queued download whose entry finished without downloading (e.g. installed from the cache)
+    fn dequeue_download(&self, filename: &str) {
+        self.queued.lock().remove(filename);
+    }
+
+    /// Registers a download attempt. `received` is what a resumed .part file already holds.
+    fn start_download(&self, filename: &str, received: u64, expected: Option<u64>) {
+        self.dequeue_download(filename);
+        self.downloads.lock().insert(filename.to_string(), ByteProgress { received, expected });
+    }
+
+    fn add_received(&self, filename: &str, bytes: u64) {
+        self.transferred.fetch_add(bytes, Ordering::Relaxed);
+        if let Some(download) = self.downloads.lock().get_mut(filename) {
+            download.received += bytes;
+        }
+    }
+
+    /// Ends a download attempt; only successful downloads keep counting towards the totals
+    fn finish_download(&self, filename: &str, success: bool) {
+        if let Some(download) = self.downloads.lock().remove(filename)
+            && success
+        {
+            self.completed_bytes.fetch_add(download.received, Ordering::Relaxed);
         }
     }
 
     /// Update the last processed mod (called from sync_all_from_entries)
-    pub fn set_last_mod(&self, filename: String) {
+    pub fn set_last_mod(&self, name: String) {
         let mut lock = self.last_mod.lock();
-        *lock = Some(filename);
+        *lock = Some(name);
     }
 
     /// Retrieve the last processed mod for UI
@@ -82,35 +295,239 @@ impl SyncProgress {
 /// Simple struct for UI to read current numbers
 pub struct SyncStats {
     pub downloaded: usize,
+    pub updated: usize,
     pub unchanged: usize,
     pub removed: usize,
+    pub skipped: usize,
     pub failed: usize,
+
+    /// Downloads currently running
+    pub active_downloads: usize,
+    /// How many entries' worth of the running downloads is done (0.5 = half of one file)
+    pub in_flight: f32,
+    /// Bytes of running and finished downloads
+    pub bytes_received: u64,
+    /// Expected size of running, queued and finished downloads, as far as it is known
+    pub bytes_expected: u64,
+    pub bytes_per_sec: f64,
+    /// Time until every download is done; only known while all running and queued downloads have a known size
+    pub eta: Option<Duration>,
+}
+
+impl SyncStats {
+    /// Entries done, counting running downloads by how far along they are
+    pub fn fraction(&self, processed: usize, total: usize) -> f32 {
+        if total == 0 {
+            return 0.0;
+        }
+        ((processed as f32 + self.in_flight) / total as f32).min(1.0)
+    }
+
+    /// e.g. `45.2 MB / 200.0 MB, 5.1 MB/s, 30s left`; None while nothing is downloading
+    pub fn transfer_summary(&self) -> Option<String> {
+        if self.active_downloads == 0 {
+            return None;
+        }
+
+        let mut summary = format!(
+            "{} / {}, {}/s",
+            format_bytes(self.bytes_received),
+            format_bytes(self.bytes_expected),
+            format_bytes(self.bytes_per_sec as u64)
+        );
+        if let Some(eta) = self.eta {
+            summary.push_str(&format!(", {} left", format_duration(eta)));
+        }
+        Some(summary)
+    }
+}
+
+/// Human-readable byte count (`1536` -> `1.5 KB`)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Short duration for ETAs (`95s` -> `1m 35s`)
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
 }
 
 
 /// Optional real-time events for UI
 #[derive(Debug, Clone)]
 pub enum SyncEvent {
-    Downloaded { filename: String },
-    Unchanged { filename: String },
-    Removed { filename: String },
-    Failed { filename: String, error: String },
+    Downloaded { filename: String, name: String },
+    Updated { filename: String, name: String },
+    Unchanged { filename: String, name: String },
+    Removed { filename: String, name: String },
+    Skipped { filename: String, name: String },
+    Failed { filename: String, name: String, error: String },
+    Retrying { filename: String, name: String, attempt: u32, max_attempts: u32, delay: Duration, error: String },
     Finished(SyncReport),
 }
 
 pub struct ModManager;
 
 impl ModManager {
-    pub async fn load_mod_entries(file: &Option<PathBuf>, url: &Option<String>) -> anyhow::Result<Vec<ModEntry>> {
-        let text = if let Some(f) = file {
-            std::fs::read_to_string(f).context("Failed to read modsfile")?
-        } else if let Some(u) = url {
-            reqwest::get(u).await?.text().await.context("Failed to fetch mods list")?
-        } else {
+    /// Loads and parses the mod list (pipe format or TOML manifest, detected automatically).
+    /// Malformed lines are reported in `ModList::diagnostics`.
+    /// The last list fetched for this instance is kept: it is only downloaded again if it changed,
+    /// and used instead if the list URL can't be reached (unless blocked by the policy).
+    /// A dry run reads the saved list but never updates it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn load_mod_entries(
+        file: &Option<PathBuf>,
+        urls: &[String],
+        client: &Client,
+        timeout: Duration,
+        mods_dir: &Path,
+        offline_policy: OfflinePolicy,
+        trusted_keys: &[VerifyingKey],
+        dry_run: bool,
+    ) -> anyhow::Result<LoadedList> {
+        if let Some(f) = file {
+            let text = std::fs::read_to_string(f).context("Failed to read modsfile")?;
+            if !trusted_keys.is_empty() {
+                let detached = fs::read_to_string(signing::with_suffix(f, signing::SIGNATURE_EXTENSION)).ok();
+                signing::verify(&text, detached.as_deref(), trusted_keys)
+                    .context(format!("Refusing mod list {}", f.display()))?;
+            }
+            return Ok(LoadedList { list: crate::types::parse_any(&text), offline_since: None, unchanged: false });
+        }
+        if urls.is_empty() {
             anyhow::bail!("Missing --modsurl or --modsfile argument");
+        }
+
+        // List URLs are tried in order; the first one that answers wins
+        let last = LastList::load(mods_dir, urls);
+        let mut fetched = Err(anyhow::anyhow!("No list URL"));
+        let mut signature = None;
+        let mut rejected = false;
+        for (i, u) in urls.iter().enumerate() {
+            // Validators only mean something to the server that issued them
+            let saved = last.as_ref().filter(|l| l.url == *u);
+            let fetch = net::fetch_list(
+                client,
+                u,
+                saved.and_then(|l| l.etag.as_deref()),
+                saved.and_then(|l| l.last_modified.as_deref()),
+            );
+            fetched = match tokio::time::timeout(timeout, fetch).await {
+                Ok(result) => result.map(|response| (u, response)).context(format!("Failed to fetch mods list from {}", u)),
+                Err(_) => Err(anyhow::anyhow!("Timed out fetching mods list from {}", u)),
+            };
+            // A list that fails its signature check is skipped like an unreachable one
+            if let Ok((_, response)) = &fetched
+                && !trusted_keys.is_empty()
+            {
+                match Self::verify_list(client, timeout, u, response, saved, trusted_keys).await {
+                    Ok(detached) => signature = detached,
+                    Err(e) => {
+                        rejected = true;
+                        fetched = Err(e);
+                    }
+                }
+            }
+            match &fetched {
+                Ok(_) => break,
+                Err(e) if i + 1 < urls.len() => eprintln!("{:#}, trying the next list URL", e),
+                Err(_) => {}
+            }
+        }
+
+        match fetched {
+            Ok((u, ListResponse::Modified { text, etag, last_modified })) => {
+                let list = crate::types::parse_any(&text);
+                // Only a list that can be synced is worth falling back to
+                if !list.has_errors()
+                    && !dry_run
+                    && let Err(e) = LastList::new(u, text, etag, last_modified, signature).save(mods_dir)
+                {
+                    eprintln!("{:#}", e);
+                }
+                Ok(LoadedList { list, offline_since: None, unchanged: false })
+            }
+            Ok((_, ListResponse::NotModified { etag, last_modified })) => {
+                let Some(last) = last else {
+                    anyhow::bail!("The list server answered 304 Not Modified without being asked");
+                };
+                let last = last.revalidated(etag, last_modified, signature);
+                if !dry_run
+                    && let Err(e) = last.save(mods_dir)
+                {
+                    eprintln!("{:#}", e);
+                }
+                Ok(LoadedList { list: crate::types::parse_any(&last.text), offline_since: None, unchanged: true })
+            }
+            // The server is up but its list can't be trusted, launching with an older one would hide that
+            Err(e) if rejected => Err(e.context("Refusing to launch with a mod list that isn't signed by a trusted key")),
+            Err(e) if offline_policy == OfflinePolicy::Block => {
+                Err(e.context("Can't reach the mod list and offline launch is disabled"))
+            }
+            Err(e) => {
+                let Some(last) = last else {
+                    return Err(e.context("Can't reach the mod list and there is no saved copy to launch offline with"));
+                };
+                eprintln!("{:#}", e);
+                // The keys may have changed since the list was saved
+                if !trusted_keys.is_empty() {
+                    signing::verify(&last.text, last.signature.as_deref(), trusted_keys)
+                        .context("Refusing to launch offline with the saved mod list")?;
+                }
+                Ok(LoadedList {
+                    list: crate::types::parse_any(&last.text),
+                    offline_since: Some(last.fetched_at_text()),
+                    unchanged: false,
+                })
+            }
+        }
+    }
+
+    /// Checks the signature of a fetched list against the pinned keys, returning the detached signature if it has one
+    async fn verify_list(
+        client: &Client,
+        timeout: Duration,
+        url: &str,
+        response: &ListResponse,
+        saved: Option<&LastList>,
+        trusted_keys: &[VerifyingKey],
+    ) -> Result<Option<String>> {
+        let text = match response {
+            ListResponse::Modified { text, .. } => text,
+            ListResponse::NotModified { .. } => &saved
+                .context("The list server answered 304 Not Modified without being asked")?
+                .text,
         };
 
-        Ok(text.lines().filter_map(|l| crate::types::parse_line(l)).collect())
+        // Fetched every time: keys may be added to it while the list stays the same
+        let detached = if signing::has_embedded_signature(text) {
+            None
+        } else {
+            match tokio::time::timeout(timeout, net::fetch_signature(client, url)).await {
+                Ok(result) => result.context(format!("Failed to fetch the signature of {}", url))?,
+                Err(_) => anyhow::bail!("Timed out fetching the signature of {}", url),
+            }
+        };
+        signing::verify(text, detached.as_deref(), trusted_keys)
+            .context(format!("Refusing mod list from {}", url))?;
+        Ok(detached)
     }
 
     /// Main sync entry point (parallel, UI-ready)
@@ -118,209 +535,823 @@ impl ModManager {
         mod_entries: Vec<ModEntry>,
         mods_dir: PathBuf,
         client: Client,
+        options: SyncOptions,
         progress: Arc<SyncProgress>,
         event_tx: Option<UnboundedSender<SyncEvent>>,
     ) -> Result<SyncReport> {
         let mods_folder = mods_dir.join("mods");
-        if !mods_folder.exists() {
+        if !mods_folder.exists() && !options.dry_run {
             fs::create_dir_all(&mods_folder)
                 .context("Failed to create mods folder")?;
         }
 
-        let results = stream::iter(mod_entries)
-            .map(|entry| {
-                let progress = progress.clone();
-                let tx = event_tx.clone();
-                let client = client.clone();
-                let mods_folder = mods_folder.clone();
-
-                async move {
-                    Self::handle_entry(
-                        entry,
-                        &mods_folder,
-                        &client,
-                        progress,
-                        tx,
-                    ).await
+        // Removed and replaced files are kept, so the whole sync can be rolled back
+        let transaction = Transaction::begin(&mods_dir);
+
+        // Files ModSync installed earlier whose entry was dropped from the list
+        let mut state = InstanceState::load(&mods_dir);
+        let mut orphans = Vec::new();
+        for (path, file) in state.orphans(&mod_entries) {
+            let orphan = ModEntry::unlisted(path);
+            if let Err(e) = orphan.checked_local_path(&mods_dir) {
+                eprintln!("Ignoring install state entry: {}", e);
+                state.forget(&orphan.filename);
+            } else if file.matches_disk(&orphan.local_path(&mods_dir)) {
+                orphans.push(orphan);
+            } else {
+                // Deleted or changed by the user since, so it's theirs now
+                state.forget(&orphan.filename);
+            }
+        }
+
+        // Exclusive mode skips what it can't read and reports it; the rest of the sync goes ahead
+        let mut extras = Vec::new();
+        let mut exclusive_errors = Vec::new();
+        if !options.exclusive_dirs.is_empty() {
+            match Allowlist::load(&mods_dir) {
+                Ok(allowlist) => {
+                    for dir in &options.exclusive_dirs {
+                        match exclusive::find_extras(&mod_entries, &mods_dir, std::slice::from_ref(dir), &allowlist) {
+                            Ok(found) => extras.extend(found),
+                            Err(e) => exclusive_errors.push(format!("{}/ not cleaned up: {:#}", dir, e)),
+                        }
+                    }
                 }
-            })
-            .buffer_unordered(8) // parallelism limit
-            .collect::<Vec<_>>()
-            .await;
+                Err(e) => exclusive_errors.push(format!("Exclusive mode skipped: {:#}", e)),
+            }
+        }
+        extras.retain(|extra| !orphans.iter().any(|o| o.filename == extra.filename));
 
+        // Unlisted files are handled first, so they're counted in the total before anything finishes
+        progress.total.fetch_add(orphans.len() + extras.len(), Ordering::Relaxed);
+
+        // Orphans were downloaded by ModSync and can be fetched again, so they are never quarantined
+        let quarantine_dir = options.quarantine.then(|| exclusive::quarantine_dir(&mods_dir));
+        let unlisted = orphans
+            .into_iter()
+            .map(|orphan| (orphan, None))
+            .chain(extras.into_iter().map(|extra| (extra, quarantine_dir.as_deref())));
+
+        let mut results = Vec::new();
+        for (entry, quarantine_dir) in unlisted {
+            let name = entry.display_name();
+            let result = match quarantine_dir {
+                _ if options.dry_run => Ok(()),
+                Some(quarantine_dir) => exclusive::quarantine(&entry, &mods_dir, quarantine_dir)
+                    .map(|target| transaction.record_moved(&entry, &target)),
+                None => transaction.remove(&entry),
+            };
+            let filename = entry.filename.clone();
+            results.push(match result {
+                Ok(()) => {
+                    progress.removed.fetch_add(1, Ordering::Relaxed);
+                    send_event(&event_tx, SyncEvent::Removed { filename, name: name.clone() });
+                    EntryResult::Removed(entry)
+                }
+                Err(e) => {
+                    progress.failed.fetch_add(1, Ordering::Relaxed);
+                    send_event(&event_tx, SyncEvent::Failed { filename, name: name.clone(), error: format!("{:#}", e) });
+                    EntryResult::Failed(entry, format!("{:#}", e))
+                }
+            });
+            if let Some(result) = results.last()
+                && !options.dry_run
+            {
+                update_state(&mut state, result, &mods_dir, None);
+            }
+            progress.set_last_mod(name);
+            progress.processed.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let state = parking_lot::Mutex::new(state);
+        let sources = parking_lot::Mutex::new(HashMap::new());
+        let bandwidth = options.max_bandwidth.map(RateLimiter::new);
+        let hosts = options.per_host_connections.map(HostLimiter::new);
+        // Files this side installs are never cleaned up for an entry that belongs to the other side
+        let installed_here: HashSet<String> = mod_entries
+            .iter()
+            .filter(|e| e.is_required() && e.env.includes(options.side))
+            .map(|e| e.relative_path().to_lowercase())
+            .collect();
+        let ctx = SyncContext {
+            mods_dir: &mods_dir,
+            client: &client,
+            options: &options,
+            state: &state,
+            transaction: &transaction,
+            progress: &progress,
+            event_tx: &event_tx,
+            sources: &sources,
+            bandwidth: bandwidth.as_ref(),
+            hosts: hosts.as_ref(),
+            installed_here: &installed_here,
+        };
+
+        // Everything that may remove a file runs first, so no removal can hit a file that was just downloaded
+        let (removals, installs): (Vec<ModEntry>, Vec<ModEntry>) = mod_entries
+            .into_iter()
+            .partition(|e| e.is_remove() || !e.env.includes(options.side));
+        // Missing files are going to be downloaded, so the ETA includes them before they start
+        if !options.dry_run && !options.offline {
+            for entry in installs.iter().filter(|e| e.is_required() && !e.local_path(&mods_dir).exists()) {
+                progress.queue_download(&entry.filename, entry.size);
+            }
+        }
+        for phase in [removals, installs] {
+            let phase_results = stream::iter(phase)
+                .map(|entry| Self::handle_entry(entry, &ctx))
+                .buffer_unordered(options.concurrency.max(1))
+                .collect::<Vec<_>>()
+                .await;
+            results.extend(phase_results);
+        }
+        let sources = sources.into_inner();
+
+        // Unchanged files are only recorded here, installs were saved as soon as they were verified
+        if !options.dry_run
+            && let Err(e) = state.lock().save(&mods_dir)
+        {
+            eprintln!("Failed to save install state: {:#}", e);
+        }
+
+        let transaction_id = match transaction.commit(options.keep_transactions) {
+            Ok(true) => Some(transaction.id().to_string()),
+            Ok(false) => None,
+            Err(e) => {
+                eprintln!("Failed to save transaction for rollback: {:#}", e);
+                None
+            }
+        };
 
         let mut downloaded = Vec::new();
+        let mut updated = Vec::new();
         let mut unchanged = Vec::new();
         let mut removed = Vec::new();
+        let mut skipped = Vec::new();
         let mut failed = Vec::new();
 
 
         for result in results {
             match result {
                 EntryResult::Downloaded(e) => downloaded.push(e),
+                EntryResult::Updated(e) => updated.push(e),
                 EntryResult::Unchanged(e) => unchanged.push(e),
                 EntryResult::Removed(e) => removed.push(e),
+                EntryResult::Skipped(e) => skipped.push(e),
                 EntryResult::Failed(e, msg) => failed.push((e, msg)),
             }
         }
 
-        println!("Downloaded: {:?}\n", downloaded);
-        println!("Unchanged: {:?}\n", unchanged);
-        println!("Removed: {:?}\n", removed);
-        println!("Failed: {:?}\n", failed);
+        let names = |entries: &[ModEntry]| {
+            entries.iter().map(|e| e.display_name()).collect::<Vec<_>>().join(", ")
+        };
+        // A sync replaces files changed by hand like any other outdated file, the plan says so
+        let mut notes = HashMap::new();
+        if options.dry_run {
+            let state = state.lock();
+            for entry in &updated {
+                if state.is_modified(entry, &entry.local_path(&mods_dir)) {
+                    notes.insert(
+                        entry.filename.clone(),
+                        "changed locally, the changes would be replaced (a backup is kept)".to_string(),
+                    );
+                }
+            }
+        }
+
+        if options.dry_run {
+            print_plan(&downloaded, &updated, &removed, &failed, &notes, &exclusive_errors);
+        } else {
+            println!("Downloaded: {}", names(&downloaded));
+            println!("Updated: {}", names(&updated));
+            println!("Unchanged: {}", names(&unchanged));
+            println!("Removed: {}", names(&removed));
+            println!("Skipped: {}", names(&skipped));
+            for (entry, error) in &failed {
+                println!("Failed: {} ({})", entry.display_name(), error);
+            }
+            for error in &exclusive_errors {
+                println!("{}", error);
+            }
+            for entry in downloaded.iter().chain(&updated) {
+                if let Some(source) = sources.get(&entry.filename)
+                    && *source != entry.url
+                {
+                    println!("Mirror: {} from {}", entry.display_name(), source);
+                }
+            }
+        }
+        if let Some(id) = &transaction_id {
+            println!("Transaction: {} (undo with `rollback`)", id);
+        }
 
         let report = SyncReport {
             downloaded,
+            updated,
             unchanged,
             removed,
+            skipped,
             failed,
+            transaction: transaction_id,
+            dry_run: options.dry_run,
+            sources,
+            notes,
+            exclusive_errors,
         };
 
         if let Some(tx) = &event_tx {
-            let _ = tx.send(SyncEvent::Finished(report.clone()))?;
+            tx.send(SyncEvent::Finished(report.clone()))?;
         }
 
         Ok(report)
     }
 
-    async fn handle_entry(
-        entry: ModEntry,
-        mods_folder: &Path,
-        client: &Client,
-        progress: Arc<SyncProgress>,
-        event_tx: Option<UnboundedSender<SyncEvent>>,
-    ) -> EntryResult {
+    async fn handle_entry(mut entry: ModEntry, ctx: &SyncContext<'_>) -> EntryResult {
+        let SyncContext { mods_dir, client, options, state, transaction, progress, event_tx, installed_here, .. } = *ctx;
         let filename = entry.filename.clone();
-        let local_path = mods_folder.join(&filename);
+        let name = entry.display_name();
+        // Parsed lists can't contain unsafe paths, but nothing outside the instance is touched either way
+        let unsafe_path = entry.checked_local_path(mods_dir).err();
+        let local_path = entry.local_path(mods_dir);
+        let belongs_here = entry.env.includes(options.side);
+        // Required mods for the other side are cleaned up if they were installed here before
+        let remove_other_side = !belongs_here
+            && entry.is_required()
+            && local_path.exists()
+            && !installed_here.contains(&entry.relative_path().to_lowercase());
 
-        let result = if entry.category.eq_ignore_ascii_case("REMOVE") {
-            // REMOVE category: delete if exists
+        let result = if let Some(error) = unsafe_path {
+            progress.failed.fetch_add(1, Ordering::Relaxed);
+            send_event(event_tx, SyncEvent::Failed { filename: filename.clone(), name: name.clone(), error: error.clone() });
+            EntryResult::Failed(entry, error)
+        } else if (belongs_here && entry.is_remove()) || remove_other_side {
+            // REMOVE category: delete if exists (into the transaction's backup)
             if local_path.exists() {
-                match fs::remove_file(&local_path) {
+                let removed = if options.dry_run { Ok(()) } else { transaction.remove(&entry) };
+                match removed {
                     Ok(_) => {
                         progress.removed.fetch_add(1, Ordering::Relaxed);
-                        send_event(&event_tx, SyncEvent::Removed { filename: filename.clone() });
+                        send_event(event_tx, SyncEvent::Removed { filename: filename.clone(), name: name.clone() });
                         EntryResult::Removed(entry)
                     }
                     Err(e) => {
                         progress.failed.fetch_add(1, Ordering::Relaxed);
-                        send_event(&event_tx, SyncEvent::Failed {
+                        send_event(event_tx, SyncEvent::Failed {
                             filename: filename.clone(),
-                            error: e.to_string(),
+                            name: name.clone(),
+                            error: format!("{:#}", e),
                         });
-                        EntryResult::Failed(entry, e.to_string())
+                        EntryResult::Failed(entry, format!("{:#}", e))
                     }
                 }
             } else {
                 progress.unchanged.fetch_add(1, Ordering::Relaxed);
-                send_event(&event_tx, SyncEvent::Unchanged { filename: filename.clone() });
+                send_event(event_tx, SyncEvent::Unchanged { filename: filename.clone(), name: name.clone() });
                 EntryResult::Unchanged(entry)
             }
+        } else if !belongs_here {
+            // Meant for the other side and nothing to clean up
+            progress.skipped.fetch_add(1, Ordering::Relaxed);
+            send_event(event_tx, SyncEvent::Skipped { filename: filename.clone(), name: name.clone() });
+            EntryResult::Skipped(entry)
         } else {
             // Required mod, or optional selected: always check
-            match ModManager::check_and_download(&entry, mods_folder, client).await {
-                Ok(true) => {
-                    // Downloaded (new file or hash mismatch)
+            let outcome = ModManager::check_and_download(&entry, ctx).await;
+            // A plan shows how much would be downloaded
+            if options.dry_run
+                && entry.size.is_none()
+                && matches!(outcome, Ok(CheckOutcome::Downloaded | CheckOutcome::Updated))
+            {
+                entry.size = net::content_length(client, &entry.url, options.request_timeout, &options.download_policy).await;
+            }
+            match outcome {
+                Ok(CheckOutcome::Downloaded) => {
+                    // New file
                     progress.downloaded.fetch_add(1, Ordering::Relaxed);
-                    send_event(&event_tx, SyncEvent::Downloaded { filename: filename.clone() });
+                    send_event(event_tx, SyncEvent::Downloaded { filename: filename.clone(), name: name.clone() });
                     EntryResult::Downloaded(entry)
                 }
-                Ok(false) => {
+                Ok(CheckOutcome::Updated) => {
+                    // Existing file had the wrong hash and was replaced
+                    progress.updated.fetch_add(1, Ordering::Relaxed);
+                    send_event(event_tx, SyncEvent::Updated { filename: filename.clone(), name: name.clone() });
+                    EntryResult::Updated(entry)
+                }
+                Ok(CheckOutcome::Unchanged) => {
                     // File exists and hash matches
                     progress.unchanged.fetch_add(1, Ordering::Relaxed);
-                    send_event(&event_tx, SyncEvent::Unchanged { filename: filename.clone() });
+                    send_event(event_tx, SyncEvent::Unchanged { filename: filename.clone(), name: name.clone() });
                     EntryResult::Unchanged(entry)
                 }
                 Err(e) => {
                     progress.failed.fetch_add(1, Ordering::Relaxed);
-                    send_event(&event_tx, SyncEvent::Failed { filename: filename.clone(), error: e.to_string() });
-                    EntryResult::Failed(entry, e.to_string())
+                    send_event(event_tx, SyncEvent::Failed { filename: filename.clone(), name: name.clone(), error: format!("{:#}", e) });
+                    EntryResult::Failed(entry, format!("{:#}", e))
                 }
             }
         };
 
-        progress.set_last_mod(filename);
+        if !options.dry_run {
+            let source = ctx.sources.lock().get(&filename).cloned();
+            let mut state = state.lock();
+            update_state(&mut state, &result, mods_dir, source.as_deref());
+            // Persist verified installs right away, so they aren't hashed again after a crash
+            if matches!(result, EntryResult::Downloaded(_) | EntryResult::Updated(_))
+                && let Err(e) = state.save(mods_dir)
+            {
+                eprintln!("Failed to save install state: {:#}", e);
+            }
+        }
+
+        progress.dequeue_download(&filename);
+        progress.set_last_mod(name);
         progress.processed.fetch_add(1, Ordering::Relaxed);
         result
     }
 
 
-    async fn check_and_download(
-        entry: &ModEntry,
-        mods_folder: &Path,
-        client: &Client,
-    ) -> Result<bool> {
-        let local_path = mods_folder.join(&entry.filename);
+    async fn check_and_download(entry: &ModEntry, ctx: &SyncContext<'_>) -> Result<CheckOutcome> {
+        let SyncContext { mods_dir, options, state, transaction, .. } = *ctx;
+        let local_path = entry.local_path(mods_dir);
 
         if local_path.exists() {
-            if let Some(expected) = &entry.sha256 {
-                let actual = Self::sha256_file(&local_path)?;
-                if actual.eq_ignore_ascii_case(expected) {
-                    return Ok(false);
-                } else {
-                    anyhow::bail!(
-                        "SHA256 mismatch for {} (expected {}, got {})",
-                        entry.filename,
-                        expected,
-                        actual
-                    );
+            let Some(expected) = &entry.hash else {
+                return Ok(CheckOutcome::Unchanged);
+            };
+
+            // Same size and mtime as when it was last verified: trust the recorded hash
+            if !options.full_verify && state.lock().is_verified(entry, &local_path) {
+                return Ok(CheckOutcome::Unchanged);
+            }
+
+            let actual = Self::hash_file_async(&local_path, expected.algorithm).await?;
+            if expected.matches(&actual) {
+                // Lets other instances reuse files this one already has
+                if let Some(cache) = &options.cache
+                    && !options.dry_run
+                {
+                    Self::store_in_cache(cache, expected, entry, &local_path).await;
                 }
+                return Ok(CheckOutcome::Unchanged);
+            }
+            if options.verify_only {
+                anyhow::bail!(
+                    "{} mismatch for {} (expected {}, got {})",
+                    expected.algorithm.label(),
+                    entry.filename,
+                    expected.hex,
+                    actual
+                );
+            }
+
+            if options.dry_run {
+                return Ok(CheckOutcome::Updated);
             }
-            return Ok(false);
+
+            // Outdated or corrupted file: replace it, keeping the old version for rollback
+            let backup = transaction.backup(entry)?;
+            if let Err(e) = Self::fetch(entry, &local_path, ctx).await {
+                transaction.discard(&backup);
+                return Err(e);
+            }
+            transaction.record_replaced(entry, backup);
+            return Ok(CheckOutcome::Updated);
         }
 
-        Self::download_mod(entry, &local_path, client).await?;
-        Ok(true)
+        if options.dry_run {
+            return Ok(CheckOutcome::Downloaded);
+        }
+        Self::fetch(entry, &local_path, ctx).await?;
+        transaction.record_added(entry);
+        Ok(CheckOutcome::Downloaded)
     }
 
-    async fn download_mod(
-        entry: &ModEntry,
-        local_path: &Path,
-        client: &Client,
-    ) -> Result<()> {
-        let bytes = client
-            .get(&entry.url)
-            .send()
-            .await
-            .context(format!("Failed to download {}", entry.filename))?
-            .bytes()
-            .await
-            .context(format!("Failed to read response for {}", entry.filename))?;
+    /// Installs the file from the download cache if possible, otherwise downloads it and caches the result
+    async fn fetch(entry: &ModEntry, local_path: &Path, ctx: &SyncContext<'_>) -> Result<()> {
+        let options = ctx.options;
+        // Only files with a known hash can be looked up
+        let cache = options.cache.as_ref().zip(entry.hash.as_ref());
+        // Mods are never edited in place, so they can share storage with the cache.
+        // Configs often are, which would change the cached copy for every instance.
+        let link = entry.target_dir() == DEFAULT_TARGET_DIR;
+
+        if let Some((cache, hash)) = cache {
+            let (cache, hash, target) = (cache.clone(), hash.clone(), local_path.to_path_buf());
+            let installed = tokio::task::spawn_blocking(move || cache.install(&hash, &target, link))
+                .await
+                .context("Cache task failed")?;
+            match installed {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(e) => eprintln!("Failed to install {} from the download cache: {:#}", entry.filename, e),
+            }
+        }
+
+        if options.offline {
+            anyhow::bail!("{} can't be downloaded in offline mode", entry.filename);
+        }
+        let source = Self::download_from_mirrors(entry, local_path, ctx).await?;
+        ctx.sources.lock().insert(entry.filename.clone(), source);
+
+        if let Some((cache, hash)) = cache {
+            Self::store_in_cache(cache, hash, entry, local_path).await;
+        }
+        Ok(())
+    }
+
+    /// Adds a verified file to the download cache. Failing to do so doesn't fail the sync.
+    async fn store_in_cache(cache: &Cache, hash: &FileHash, entry: &ModEntry, local_path: &Path) {
+        let link = entry.target_dir() == DEFAULT_TARGET_DIR;
+        let (cache, hash, source) = (cache.clone(), hash.clone(), local_path.to_path_buf());
+        let stored = tokio::task::spawn_blocking(move || cache.store(&hash, &source, link)).await;
+        if let Ok(Err(e)) = stored {
+            eprintln!("Failed to add {} to the download cache: {:#}", entry.filename, e);
+        }
+    }
+
+    /// Tries the download URL and then each mirror until one delivers the file.
+    /// The hash decides what is correct, so a mirror serving something else counts as failed.
+    /// Returns the URL the file was downloaded from.
+    async fn download_from_mirrors(entry: &ModEntry, local_path: &Path, ctx: &SyncContext<'_>) -> Result<String> {
+        let urls: Vec<&str> = entry.urls().collect();
+        let mut errors = Vec::new();
+        for (i, url) in urls.iter().enumerate() {
+            // Without a hash nothing proves two servers have the same file, so don't mix their bytes
+            if i > 0 && entry.hash.is_none() {
+                discard_part(local_path);
+            }
+            match Self::download_with_retries(entry, url, local_path, ctx).await {
+                Ok(()) => return Ok(url.to_string()),
+                Err(e) if urls.len() == 1 => return Err(e),
+                Err(e) => {
+                    if i + 1 < urls.len() {
+                        eprintln!("{:#}, trying the next mirror", e);
+                    }
+                    errors.push(format!("{}: {:#}", url, e));
+                }
+            }
+        }
+        anyhow::bail!("All {} URLs failed ({})", urls.len(), errors.join("; "))
+    }
 
-        fs::write(local_path, &bytes)
-            .context(format!("Failed to write {}", entry.filename))?;
+    /// Retries transient download failures with exponential backoff (or the server's Retry-After).
+    /// The .part file survives between attempts, so each retry resumes where the last one stopped.
+    async fn download_with_retries(entry: &ModEntry, url: &str, local_path: &Path, ctx: &SyncContext<'_>) -> Result<()> {
+        let SyncContext { options, progress, event_tx, .. } = *ctx;
+        let mut retry = 0;
+        loop {
+            let result = Self::download_mod(entry, url, local_path, ctx).await;
+            progress.finish_download(&entry.filename, result.is_ok());
+            let error = match result {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+            let Some(transient) = error.downcast_ref::<Transient>() else {
+                return Err(error);
+            };
+            if retry >= options.retries {
+                return Err(error);
+            }
+
+            retry += 1;
+            let delay = net::retry_delay(retry, transient.retry_after);
+            send_event(event_tx, SyncEvent::Retrying {
+                filename: entry.filename.clone(),
+                name: entry.display_name(),
+                attempt: retry + 1,
+                max_attempts: options.retries + 1,
+                delay,
+                error: transient.to_string(),
+            });
+            tokio::time::sleep(delay).await;
+        }
+    }
 
-        if let Some(expected) = &entry.sha256 {
-            let actual = Self::sha256_file(local_path)?;
-            if !actual.eq_ignore_ascii_case(expected) {
+    async fn download_mod(entry: &ModEntry, url: &str, local_path: &Path, ctx: &SyncContext<'_>) -> Result<()> {
+        let SyncContext { client, options, progress, bandwidth, hosts, .. } = *ctx;
+        options.download_policy
+            .check(url)
+            .map_err(|reason| anyhow::anyhow!("Refusing to download {}: {}", entry.filename, reason))?;
+
+        // Target folders other than mods/ are created on demand
+        if let Some(parent) = local_path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create folder for {}", entry.filename))?;
+        }
+
+        // Download next to the target and only move it into place once it's complete and verified,
+        // so an interrupted download never looks like an installed mod
+        let part_path = part_path(local_path);
+        let validator_path = validator_path(local_path);
+        let mut resume_from = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+        let expected_hash = entry.hash.as_ref().map(|h| h.to_string()).unwrap_or_default();
+        let origin = read_part_origin(local_path);
+        let validator = origin.as_ref().map(|(_, validator)| validator.clone()).filter(|v| !v.is_empty());
+        // A .part of an older version of the file can't be completed. Without a hash
+        // only the server can tell whether the file is still the one the .part came from.
+        let resumable = origin.is_some_and(|(hash, _)| hash == expected_hash)
+            && (entry.hash.is_some() || validator.is_some());
+        if resume_from > 0 && !resumable {
+            discard_part(local_path);
+            resume_from = 0;
+        }
+
+        // Held until this attempt is over, retries wait their turn again
+        let _host_permit = match hosts {
+            Some(hosts) => hosts.acquire(url).await,
+            None => None,
+        };
+        let mut request = client.get(url);
+        if resume_from > 0 {
+            request = request.header(RANGE, format!("bytes={}-", resume_from));
+            // A changed file comes back whole (200) instead of as a range of the new one
+            if let Some(validator) = &validator {
+                request = request.header(IF_RANGE, validator.as_str());
+            }
+        }
+        let mut response = match tokio::time::timeout(options.request_timeout, request.send()).await {
+            Ok(Ok(response)) => response,
+            Ok(Err(e)) => {
+                // A redirect the policy refused isn't going to be allowed on a retry either
+                if let Some(reason) = net::blocked_reason(&e) {
+                    anyhow::bail!("Refusing to download {}: redirected, {}", entry.filename, reason);
+                }
+                return Err(Transient::new(format!("Failed to download {}: {}", entry.filename, e)).into());
+            }
+            Err(_) => return Err(Transient::new(format!(
+                "No response for {} within {}s",
+                entry.filename,
+                options.request_timeout.as_secs()
+            )).into()),
+        };
+
+        // Hash while streaming; a resumed download first needs the hash state of what's already on disk
+        let mut hasher = match &entry.hash {
+            Some(hash) if resume_from > 0 => Some(Self::hasher_for_file(&part_path, hash.algorithm).await?),
+            Some(hash) => Some(Hasher::new(hash.algorithm)),
+            None => None,
+        };
+
+        // 416: the partial file may already hold the whole body. The hash checks that below;
+        // without one, the size the server reports has to match.
+        let already_complete = resume_from > 0
+            && response.status() == StatusCode::RANGE_NOT_SATISFIABLE
+            && match net::content_range_total(response.headers()) {
+                Some(total) => total == resume_from,
+                None => entry.hash.is_some(),
+            };
+        if resume_from > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE && !already_complete {
+            discard_part(local_path);
+            return Err(Transient::new(format!("Can't resume {}, starting over", entry.filename)).into());
+        }
+        if !already_complete {
+            if net::is_transient_status(response.status()) {
+                return Err(Transient {
+                    message: format!("Failed to download {}: HTTP {}", entry.filename, response.status()),
+                    retry_after: net::retry_after(response.headers()),
+                }.into());
+            }
+            response = response
+                .error_for_status()
+                .context(format!("Failed to download {}", entry.filename))?;
+
+            // Servers without Range support, and servers whose file changed, send the whole file again
+            let append = resume_from > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+            if append && net::content_range_start(response.headers()) != Some(resume_from) {
+                discard_part(local_path);
+                return Err(Transient::new(format!(
+                    "Server sent the wrong part of {}, starting over",
+                    entry.filename
+                )).into());
+            }
+            if !append {
+                hasher = entry.hash.as_ref().map(|hash| Hasher::new(hash.algorithm));
+                // Lets the next attempt resume only if the entry and the file are still these
+                let validator = net::resume_validator(response.headers()).unwrap_or_default();
+                let _ = fs::write(&validator_path, format!("{}\n{}\n", expected_hash, validator));
+            }
+            let already_received = if append { resume_from } else { 0 };
+            let expected_size = response
+                .content_length()
+                .map(|len| already_received + len)
+                .or(entry.size);
+            progress.start_download(&entry.filename, already_received, expected_size);
+
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append)
+                .truncate(!append)
+                .open(&part_path)
+                .await
+                .context(format!("Failed to write {}", entry.filename))?;
+
+            // A connection that stops sending data counts as failed, so it can be retried
+            while let Some(chunk) = tokio::time::timeout(options.stall_timeout, response.chunk())
+                .await
+                .map_err(|_| Transient::new(format!(
+                    "Download of {} stalled (no data for {}s)",
+                    entry.filename,
+                    options.stall_timeout.as_secs()
+                )))?
+                .map_err(|e| Transient::new(format!("Failed to read response for {}: {}", entry.filename, e)))?
+            {
+                if let Some(hasher) = &mut hasher {
+                    hasher.update(&chunk);
+                }
+                if let Some(bandwidth) = bandwidth {
+                    bandwidth.consume(chunk.len()).await;
+                }
+                progress.add_received(&entry.filename, chunk.len() as u64);
+                file.write_all(&chunk)
+                    .await
+                    .context(format!("Failed to write {}", entry.filename))?;
+            }
+            file.sync_all()
+                .await
+                .context(format!("Failed to write {}", entry.filename))?;
+        }
+
+        if let (Some(expected), Some(hasher)) = (&entry.hash, hasher) {
+            let actual = hasher.finalize_hex();
+            if !expected.matches(&actual) {
+                // Can't tell which part is bad, start over next time
+                discard_part(local_path);
                 anyhow::bail!(
-                    "SHA256 mismatch for {} (expected {}, got {})",
+                    "{} mismatch for {} (expected {}, got {})",
+                    expected.algorithm.label(),
                     entry.filename,
-                    expected,
+                    expected.hex,
                     actual
                 );
             }
         }
 
+        fs::rename(&part_path, local_path)
+            .context(format!("Failed to move {} into place", entry.filename))?;
+        let _ = fs::remove_file(&validator_path);
+
         Ok(())
     }
 
-    pub(crate) fn sha256_file(path: &Path) -> Result<String> {
-        let data = fs::read(path).context("Failed to read file for hashing")?;
-        let mut hasher = Sha256::new();
-        hasher.update(&data);
-        Ok(format!("{:x}", hasher.finalize()))
+    /// Hashes a file in fixed-size chunks, so large files never sit in memory. Returns lowercase hex.
+    pub(crate) fn hash_file(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+        Ok(Self::hasher_for_file_blocking(path, algorithm)?.finalize_hex())
+    }
+
+    /// Like hash_file, but runs on the blocking thread pool instead of an executor thread
+    async fn hash_file_async(path: &Path, algorithm: HashAlgorithm) -> Result<String> {
+        Ok(Self::hasher_for_file(path, algorithm).await?.finalize_hex())
+    }
+
+    async fn hasher_for_file(path: &Path, algorithm: HashAlgorithm) -> Result<Hasher> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || Self::hasher_for_file_blocking(&path, algorithm))
+            .await
+            .context("Hashing task failed")?
+    }
+
+    fn hasher_for_file_blocking(path: &Path, algorithm: HashAlgorithm) -> Result<Hasher> {
+        let mut file = fs::File::open(path).context("Failed to read file for hashing")?;
+        let mut hasher = Hasher::new(algorithm);
+        let mut buffer = vec![0u8; HASH_CHUNK_SIZE];
+        loop {
+            let read = file.read(&mut buffer).context("Failed to read file for hashing")?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+        }
+        Ok(hasher)
     }
 }
 
+/// Everything a single entry needs while syncing, shared by all parallel tasks
+#[derive(Clone, Copy)]
+struct SyncContext<'a> {
+    mods_dir: &'a Path,
+    client: &'a Client,
+    options: &'a SyncOptions,
+    state: &'a parking_lot::Mutex<InstanceState>,
+    transaction: &'a Transaction,
+    progress: &'a SyncProgress,
+    event_tx: &'a Option<UnboundedSender<SyncEvent>>,
+    sources: &'a parking_lot::Mutex<HashMap<String, String>>,
+    bandwidth: Option<&'a RateLimiter>,
+    hosts: Option<&'a HostLimiter>,
+    installed_here: &'a HashSet<String>, // lowercase relative paths of files required on this side
+}
+
+/// What check_and_download did with a required file
+enum CheckOutcome {
+    Downloaded,
+    Updated,
+    Unchanged,
+}
+
 /// Internal per-entry result
 enum EntryResult {
     Downloaded(ModEntry),
+    Updated(ModEntry),
     Unchanged(ModEntry),
     Removed(ModEntry),
+    Skipped(ModEntry),
     Failed(ModEntry, String),
 }
 
+/// Temporary download location next to the final file (`example.jar` -> `example.jar.part`)
+pub(crate) fn part_path(local_path: &Path) -> PathBuf {
+    let mut name = local_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    local_path.with_file_name(name)
+}
+
+/// What a .part file was downloaded for (`example.jar.part.validator`): the hash the list gave
+/// and the ETag or Last-Modified of the response, one per line and empty if there was none
+fn validator_path(local_path: &Path) -> PathBuf {
+    let mut name = part_path(local_path).into_os_string();
+    name.push(".validator");
+    PathBuf::from(name)
+}
+
+/// Reads the hash and validator recorded for a .part file
+fn read_part_origin(local_path: &Path) -> Option<(String, String)> {
+    let text = fs::read_to_string(validator_path(local_path)).ok()?;
+    let mut lines = text.lines().map(str::trim);
+    let hash = lines.next().unwrap_or_default().to_string();
+    let validator = lines.next().unwrap_or_default().to_string();
+    Some((hash, validator))
+}
+
+/// Deletes an unfinished download, so the next attempt starts from the beginning
+fn discard_part(local_path: &Path) {
+    let _ = fs::remove_file(part_path(local_path));
+    let _ = fs::remove_file(validator_path(local_path));
+}
+
+/// Prints what a --dry-run sync would do
+fn print_plan(
+    downloads: &[ModEntry],
+    updates: &[ModEntry],
+    removals: &[ModEntry],
+    failures: &[(ModEntry, String)],
+    notes: &HashMap<String, String>,
+    exclusive_errors: &[String],
+) {
+    let describe = |entry: &ModEntry| {
+        let description = match entry.size {
+            Some(size) => format!("{} ({})", entry.relative_path(), format_bytes(size)),
+            None => entry.relative_path(),
+        };
+        match notes.get(&entry.filename) {
+            Some(note) => format!("{}: {}", description, note),
+            None => description,
+        }
+    };
+
+    println!("Plan (nothing was changed):");
+    for entry in downloads {
+        println!("  download {}", describe(entry));
+    }
+    for entry in updates {
+        println!("  update   {}", describe(entry));
+    }
+    for entry in removals {
+        println!("  remove   {}", entry.relative_path());
+    }
+    for (entry, reason) in failures {
+        println!("  failed   {}: {}", entry.relative_path(), reason);
+    }
+    for error in exclusive_errors {
+        println!("  {}", error);
+    }
+
+    let transfers = downloads.iter().chain(updates);
+    let known: u64 = transfers.clone().filter_map(|e| e.size).sum();
+    let unknown = transfers.filter(|e| e.size.is_none()).count();
+    print!(
+        "{} download(s), {} update(s), {} removal(s), {} failed; {} to download",
+        downloads.len(), updates.len(), removals.len(), failures.len(), format_bytes(known)
+    );
+    if unknown > 0 {
+        print!(" plus {} file(s) of unknown size", unknown);
+    }
+    println!();
+}
+
+/// Records or forgets the file of a finished entry; `source` is the URL it was downloaded from, if it was
+fn update_state(state: &mut InstanceState, result: &EntryResult, mods_dir: &Path, source: Option<&str>) {
+    match result {
+        EntryResult::Downloaded(e) | EntryResult::Updated(e) | EntryResult::Unchanged(e) if e.is_required() => {
+            if let Err(err) = state.record(e, mods_dir, source) {
+                eprintln!("{:#}", err);
+            }
+        }
+        EntryResult::Removed(e) | EntryResult::Unchanged(e) => state.forget(&e.relative_path()),
+        _ => {}
+    }
+}
+
 fn send_event(tx: &Option<UnboundedSender<SyncEvent>>, event: SyncEvent) {
     if let Some(tx) = tx {
         let _ = tx.send(event);
@@ -1,2 +1,10 @@
+pub mod cache;
+pub mod config;
+pub mod exclusive;
+pub mod last_list;
 pub mod lib;
-pub use lib::*;
\ No newline at end of file
+pub mod net;
+pub mod signing;
+pub mod state;
+pub mod transaction;
+pub use lib::*;
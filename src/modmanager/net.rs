@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use reqwest::header::{
+    HeaderMap, HeaderName, CONTENT_LENGTH, CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::{Client, StatusCode};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Longest we wait between two attempts, even if the server asks for more
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Delay before the first retry; doubles with every further attempt
+pub const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// A download failure that is worth retrying (network trouble, 5xx, 429, stalls)
+#[derive(Debug)]
+pub struct Transient {
+    pub message: String,
+    pub retry_after: Option<Duration>, // delay requested by the server
+}
+
+impl Transient {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into(), retry_after: None }
+    }
+}
+
+impl fmt::Display for Transient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Transient {}
+
+/// Hosts downloads may come from without being configured: the big mod hosts
+pub const DEFAULT_ALLOWED_HOSTS: &[&str] = &[
+    "cdn.modrinth.com",
+    "github.com",
+    "objects.githubusercontent.com",
+    "release-assets.githubusercontent.com",
+    "raw.githubusercontent.com",
+    "edge.forgecdn.net",
+    "mediafilez.forgecdn.net",
+];
+
+/// Redirects followed before a request fails, the same as reqwest's default
+const MAX_REDIRECTS: usize = 10;
+
+/// HTTP client for the mod list, or for downloads when a policy is given.
+/// The policy also applies to every redirect, so an allowed host can't forward a download elsewhere.
+pub fn build_client(connect_timeout: Duration, policy: Option<DownloadPolicy>) -> reqwest::Result<Client> {
+    let mut builder = Client::builder().connect_timeout(connect_timeout);
+    if let Some(policy) = policy {
+        builder = builder.redirect(reqwest::redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                return attempt.error("too many redirects");
+            }
+            match policy.check(attempt.url().as_str()) {
+                Ok(()) => attempt.follow(),
+                Err(reason) => attempt.error(Blocked(reason)),
+            }
+        }));
+    }
+    builder.build()
+}
+
+/// Where downloads may come from
+#[derive(Debug, Clone)]
+pub struct DownloadPolicy {
+    allow_http: bool,
+    allowed_hosts: Option<Vec<String>>, // lowercase; None allows every host
+}
+
+impl DownloadPolicy {
+    /// Allows `hosts` (`*.example.com` includes subdomains, `*` allows any host).
+    /// IPv6 addresses may be given with or without brackets.
+    pub fn new(allow_http: bool, hosts: impl IntoIterator<Item = String>) -> Self {
+        let hosts: Vec<String> = hosts
+            .into_iter()
+            .map(|h| h.trim().trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase())
+            .collect();
+        let allowed_hosts = (!hosts.iter().any(|h| h == "*")).then_some(hosts);
+        Self { allow_http, allowed_hosts }
+    }
+
+    /// Returns why a URL may not be downloaded from, if it may not
+    pub fn check(&self, url: &str) -> Result<(), String> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| format!("invalid URL {}: {}", url, e))?;
+        if parsed.scheme() != "https" && !self.allow_http {
+            return Err(format!("{} is not HTTPS (set allow_http in .modsync/config.toml to allow it)", url));
+        }
+
+        let Some(allowed) = &self.allowed_hosts else {
+            return Ok(());
+        };
+        let host = parsed
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        let is_allowed = allowed.iter().any(|pattern| match pattern.strip_prefix("*.") {
+            Some(domain) => host == domain || host.ends_with(&format!(".{}", domain)),
+            None => host == *pattern,
+        });
+        if is_allowed {
+            Ok(())
+        } else {
+            Err(format!("host {} is not allowed (add it to allowed_hosts in .modsync/config.toml)", host))
+        }
+    }
+}
+
+/// A redirect the download policy refused to follow
+#[derive(Debug)]
+pub struct Blocked(pub String);
+
+impl fmt::Display for Blocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Blocked {}
+
+/// The policy violation behind a failed request, if that's why it failed
+pub fn blocked_reason(error: &reqwest::Error) -> Option<String> {
+    let mut source = std::error::Error::source(error);
+    while let Some(e) = source {
+        if let Some(blocked) = e.downcast_ref::<Blocked>() {
+            return Some(blocked.0.clone());
+        }
+        source = e.source();
+    }
+    None
+}
+
+/// Statuses that usually go away on their own
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+/// Parses a Retry-After header given either in seconds or as an HTTP date
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+/// Exponential backoff for the given retry (1-based), unless the server asked for a delay
+pub fn retry_delay(retry: u32, requested: Option<Duration>) -> Duration {
+    let backoff = INITIAL_RETRY_DELAY.saturating_mul(1 << (retry - 1).min(16));
+    requested.unwrap_or(backoff).min(MAX_RETRY_DELAY)
+}
+
+/// Size of a remote file from a HEAD request, None if the server doesn't say or can't be reached.
+/// URLs the download policy refuses aren't contacted at all.
+pub async fn content_length(client: &Client, url: &str, timeout: Duration, policy: &DownloadPolicy) -> Option<u64> {
+    policy.check(url).ok()?;
+    let response = tokio::time::timeout(timeout, client.head(url).send()).await.ok()?.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    // Response::content_length describes the (empty) body of a HEAD response, not the file
+    response.headers().get(CONTENT_LENGTH)?.to_str().ok()?.parse().ok()
+}
+
+/// What to send as `If-Range` when resuming this response later: a strong ETag, or else Last-Modified
+pub fn resume_validator(headers: &HeaderMap) -> Option<String> {
+    header_text(headers, ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header_text(headers, LAST_MODIFIED))
+}
+
+/// First byte of a 206 response (`Content-Range: bytes 100-199/200`)
+pub fn content_range_start(headers: &HeaderMap) -> Option<u64> {
+    content_range(headers)?.0.split_once('-')?.0.trim().parse().ok()
+}
+
+/// Length of the whole file according to Content-Range (`bytes 100-199/200`, or `bytes */200` with a 416)
+pub fn content_range_total(headers: &HeaderMap) -> Option<u64> {
+    content_range(headers)?.1.trim().parse().ok()
+}
+
+fn content_range(headers: &HeaderMap) -> Option<(&str, &str)> {
+    headers.get(CONTENT_RANGE)?.to_str().ok()?.trim().strip_prefix("bytes ")?.split_once('/')
+}
+
+/// Answer to a conditional request for the mod list
+pub enum ListResponse {
+    /// The list changed, or there was nothing to compare against
+    Modified {
+        text: String,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+    /// 304: the saved copy is still current. Carries new validators if the server sent any.
+    NotModified {
+        etag: Option<String>,
+        last_modified: Option<String>,
+    },
+}
+
+/// Fetches the mod list, sending the validators of the saved copy so an unchanged list costs a 304
+pub async fn fetch_list(
+    client: &Client,
+    url: &str,
+    etag: Option<&str>,
+    last_modified: Option<&str>,
+) -> reqwest::Result<ListResponse> {
+    let mut request = client.get(url);
+    if let Some(etag) = etag {
+        request = request.header(IF_NONE_MATCH, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        request = request.header(IF_MODIFIED_SINCE, last_modified);
+    }
+
+    let response = request.send().await?;
+    let etag = header_text(response.headers(), ETAG);
+    let last_modified = header_text(response.headers(), LAST_MODIFIED);
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(ListResponse::NotModified { etag, last_modified });
+    }
+
+    let text = response.error_for_status()?.text().await?;
+    Ok(ListResponse::Modified { text, etag, last_modified })
+}
+
+/// Fetches the detached signature of a list (`<url>.sig`), None if the server has none
+pub async fn fetch_signature(client: &Client, list_url: &str) -> anyhow::Result<Option<String>> {
+    // The query string stays at the end, raw URLs of private repositories carry a token there
+    let mut url = reqwest::Url::parse(list_url)?;
+    url.set_path(&format!("{}.{}", url.path(), crate::modmanager::signing::SIGNATURE_EXTENSION));
+
+    let response = client.get(url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    Ok(Some(response.error_for_status()?.text().await?))
+}
+
+fn header_text(headers: &HeaderMap, name: HeaderName) -> Option<String> {
+    Some(headers.get(name)?.to_str().ok()?.to_string())
+}
+
+/// Token bucket shared by all downloads, so together they stay below a bandwidth cap.
+/// Downloads that take more than is available sleep until the debt is paid off.
+pub struct RateLimiter {
+    bytes_per_sec: f64,
+    bucket: parking_lot::Mutex<(f64, Instant)>, // available bytes (negative: debt), last refill
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1) as f64;
+        Self { bytes_per_sec, bucket: parking_lot::Mutex::new((bytes_per_sec, Instant::now())) }
+    }
+
+    /// Takes `bytes` out of the bucket, waiting if the cap is exceeded
+    pub async fn consume(&self, bytes: usize) {
+        let wait = {
+            let mut bucket = self.bucket.lock();
+            let (available, last) = &mut *bucket;
+            let now = Instant::now();
+            // At most one second worth of bytes can be saved up for a burst
+            *available = (*available + now.duration_since(*last).as_secs_f64() * self.bytes_per_sec)
+                .min(self.bytes_per_sec);
+            *last = now;
+            *available -= bytes as f64;
+            (*available < 0.0).then(|| Duration::from_secs_f64(-*available / self.bytes_per_sec))
+        };
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Limits how many downloads run against the same host at once
+pub struct HostLimiter {
+    per_host: usize,
+    hosts: parking_lot::Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(per_host: usize) -> Self {
+        Self { per_host: per_host.max(1), hosts: parking_lot::Mutex::new(HashMap::new()) }
+    }
+
+    /// Waits for a free connection slot on the URL's host; the slot is free again when the permit is dropped
+    pub async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        let host = reqwest::Url::parse(url).ok()?.host_str()?.to_ascii_lowercase();
+        let semaphore = self
+            .hosts
+            .lock()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.per_host)))
+            .clone();
+        semaphore.acquire_owned().await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn policy(allow_http: bool, hosts: &[&str]) -> DownloadPolicy {
+        DownloadPolicy::new(allow_http, hosts.iter().map(|h| h.to_string()))
+    }
+
+    #[test]
+    fn policy_checks_scheme_and_host() {
+        let cases = [
+            (policy(false, &["cdn.modrinth.com"]), "https://cdn.modrinth.com/a.jar", true),
+            (policy(false, &["cdn.modrinth.com"]), "https://CDN.Modrinth.com/a.jar", true),
+            (policy(false, &["cdn.modrinth.com"]), "https://evil.com/cdn.modrinth.com/a.jar", false),
+            (policy(false, &["cdn.modrinth.com"]), "https://cdn.modrinth.com.evil.com/a.jar", false),
+            (policy(false, &["*.example.com"]), "https://dl.example.com/a.jar", true),
+            (policy(false, &["*.example.com"]), "https://a.b.example.com/a.jar", true),
+            (policy(false, &["*.example.com"]), "https://example.com/a.jar", true),
+            (policy(false, &["*.example.com"]), "https://evilexample.com/a.jar", false),
+            (policy(false, &["*.example.com"]), "https://example.com.evil.org/a.jar", false),
+            (policy(false, &["::1"]), "https://[::1]:8443/a.jar", true),
+            (policy(false, &["[::1]"]), "https://[::1]/a.jar", true),
+            (policy(false, &["::1"]), "https://[::2]/a.jar", false),
+            (policy(false, &["example.com"]), "http://example.com/a.jar", false),
+            (policy(true, &["example.com"]), "http://example.com/a.jar", true),
+            (policy(true, &["example.com"]), "http://other.com/a.jar", false),
+            (policy(false, &["*"]), "https://anything.org/a.jar", true),
+            (policy(false, &["*"]), "http://anything.org/a.jar", false),
+            (policy(true, &["*"]), "http://anything.org/a.jar", true),
+            (policy(true, &["*"]), "not a url", false),
+            (policy(false, &[]), "https://cdn.modrinth.com/a.jar", false),
+        ];
+        for (policy, url, allowed) in cases {
+            assert_eq!(policy.check(url).is_ok(), allowed, "{} with {:?}", url, policy);
+        }
+    }
+
+    #[test]
+    fn policy_explains_refusals() {
+        let error = policy(false, &["example.com"]).check("http://example.com/a.jar").unwrap_err();
+        assert!(error.contains("not HTTPS"), "{}", error);
+        let error = policy(false, &["example.com"]).check("https://evil.com/a.jar").unwrap_err();
+        assert!(error.contains("host evil.com is not allowed"), "{}", error);
+    }
+
+    /// Answers a single request with a redirect to `location`
+    fn redirect_server(location: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+                line.clear();
+            }
+            let location = location.replace("{port}", &address.port().to_string());
+            let _ = write!(stream, "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n", location);
+        });
+        format!("http://{}/a.jar", address)
+    }
+
+    #[tokio::test]
+    async fn refused_redirect_is_reported() {
+        let cases = [
+            ("https://evil.example.org/a.jar", "host evil.example.org is not allowed"),
+            ("http://127.0.0.1:{port}/b.jar", "not HTTPS"),
+        ];
+        for (location, expected) in cases {
+            let url = redirect_server(location);
+            // Plain HTTP is allowed unless the case is about it; the client only checks redirects, download_mod checks the first URL
+            let policy = policy(!location.starts_with("http:"), &["127.0.0.1"]);
+            let client = build_client(Duration::from_secs(5), Some(policy)).unwrap();
+            let error = client.get(&url).send().await.unwrap_err();
+            let reason = blocked_reason(&error).unwrap_or_else(|| panic!("not blocked: {}", error));
+            assert!(reason.contains(expected), "{}", reason);
+        }
+    }
+
+    #[test]
+    fn unrelated_errors_are_not_blocked() {
+        let error = reqwest::blocking::get("http://127.0.0.1:1/a.jar").unwrap_err();
+        assert_eq!(blocked_reason(&error), None);
+    }
+
+    fn retry_after_header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn parses_retry_after() {
+        assert_eq!(retry_after(&retry_after_header("120")), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(&retry_after_header(" 0 ")), Some(Duration::ZERO));
+        assert_eq!(retry_after(&retry_after_header("soon")), None);
+        assert_eq!(retry_after(&retry_after_header("-5")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+
+        // Dates in the past mean "now"
+        assert_eq!(retry_after(&retry_after_header("Sun, 06 Nov 1994 08:49:37 GMT")), Some(Duration::ZERO));
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(90));
+        let delay = retry_after(&retry_after_header(&later)).unwrap();
+        assert!(delay > Duration::from_secs(85) && delay <= Duration::from_secs(90), "{:?}", delay);
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_cap() {
+        let delays: Vec<u64> = (1..=8).map(|retry| retry_delay(retry, None).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(retry_delay(1000, None), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn server_delay_wins_but_is_capped() {
+        assert_eq!(retry_delay(5, Some(Duration::from_secs(3))), Duration::from_secs(3));
+        assert_eq!(retry_delay(1, Some(Duration::ZERO)), Duration::ZERO);
+        assert_eq!(retry_delay(1, Some(Duration::from_secs(3600))), MAX_RETRY_DELAY);
+    }
+
+    #[test]
+    fn only_temporary_statuses_are_transient() {
+        for status in [429, 408, 500, 502, 503] {
+            assert!(is_transient_status(StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+        for status in [200, 403, 404, 410, 416] {
+            assert!(!is_transient_status(StatusCode::from_u16(status).unwrap()), "{}", status);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::OsRng;
+
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Marks a signature line, embedded in the list or in a detached `.sig` file:
+/// `# modsync-signature: <public key> <signature>`, both base64
+pub const SIGNATURE_PREFIX: &str = "# modsync-signature:";
+
+/// Appended to the list file or URL for the detached signature
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Public key in the form `--pubkey` and `public_keys` expect
+pub fn public_key_text(key: &VerifyingKey) -> String {
+    BASE64.encode(key.as_bytes())
+}
+
+pub fn parse_public_key(text: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = BASE64
+        .decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context(format!("Invalid public key `{}` (expected 32 bytes in base64)", text.trim()))?;
+    VerifyingKey::from_bytes(&bytes).context(format!("Invalid public key `{}`", text.trim()))
+}
+
+/// Reads a secret key written by `key generate`
+pub fn load_signing_key(path: &Path) -> Result<SigningKey> {
+    let text = fs::read_to_string(path).context(format!("Failed to read key {}", path.display()))?;
+    let bytes: [u8; 32] = BASE64
+        .decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .context(format!("{} is not a ModSync signing key", path.display()))?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Creates a new secret key at `path` (never overwriting one) and its public key next to it as `<path>.pub`
+pub fn generate_key(path: &Path) -> Result<SigningKey> {
+    let key = SigningKey::generate(&mut OsRng);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    // Only the owner may read the secret key
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options
+        .open(path)
+        .context(format!("Failed to create key {}", path.display()))?;
+    writeln!(file, "{}", BASE64.encode(key.to_bytes()))
+        .context(format!("Failed to write key {}", path.display()))?;
+
+    fs::write(public_key_path(path), format!("{}\n", public_key_text(&key.verifying_key())))
+        .context(format!("Failed to write public key for {}", path.display()))?;
+    Ok(key)
+}
+
+/// Moves the key at `path` to `<path>.old` and generates a new one in its place.
+/// Returns the old key, so lists can be signed with both until every player pinned the new one.
+pub fn rotate_key(path: &Path) -> Result<(SigningKey, SigningKey)> {
+    let old = load_signing_key(path)?;
+    let old_path = with_suffix(path, "old");
+    if old_path.exists() {
+        anyhow::bail!("{} already exists, delete it once nobody pins that key anymore", old_path.display());
+    }
+    fs::rename(path, &old_path).context(format!("Failed to move {} to {}", path.display(), old_path.display()))?;
+    let _ = fs::rename(public_key_path(path), public_key_path(&old_path));
+
+    let new = generate_key(path)?;
+    Ok((old, new))
+}
+
+fn public_key_path(key_path: &Path) -> PathBuf {
+    with_suffix(key_path, "pub")
+}
+
+/// `mods.txt` -> `mods.txt.<suffix>`
+pub fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// What gets signed: the list without its signature lines, ending in a newline
+fn signed_content(text: &str) -> String {
+    let mut content: String = text
+        .split_inclusive('\n')
+        .filter(|line| !line.trim_start().starts_with(SIGNATURE_PREFIX))
+        .collect();
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content
+}
+
+/// Signature lines of `text`, one per key
+pub fn signature_lines(text: &str, keys: &[SigningKey]) -> String {
+    let content = signed_content(text);
+    keys.iter()
+        .map(|key| {
+            let signature = key.sign(content.as_bytes());
+            format!(
+                "{} {} {}\n",
+                SIGNATURE_PREFIX,
+                public_key_text(&key.verifying_key()),
+                BASE64.encode(signature.to_bytes())
+            )
+        })
+        .collect()
+}
+
+/// The list with its old signature lines replaced by new ones at the end
+pub fn sign_embedded(text: &str, keys: &[SigningKey]) -> String {
+    signed_content(text) + &signature_lines(text, keys)
+}
+
+/// Returns true if the list carries its own signature
+pub fn has_embedded_signature(text: &str) -> bool {
+    text.lines().any(|line| line.trim_start().starts_with(SIGNATURE_PREFIX))
+}
+
+/// Checks that the list is signed by one of the trusted keys, using the embedded signature lines,
+/// or those of a detached signature if there are none
+pub fn verify(text: &str, detached: Option<&str>, trusted: &[VerifyingKey]) -> Result<()> {
+    let source = if has_embedded_signature(text) { text } else { detached.unwrap_or_default() };
+    let content = signed_content(text);
+
+    let mut signers = Vec::new();
+    for line in source.lines() {
+        let Some(rest) = line.trim().strip_prefix(SIGNATURE_PREFIX) else {
+            continue;
+        };
+        let mut parts = rest.split_whitespace();
+        let (Some(key), Some(signature), None) = (parts.next(), parts.next(), parts.next()) else {
+            anyhow::bail!("Malformed signature line `{}`", line.trim());
+        };
+        let key = parse_public_key(key)?;
+        let signature = BASE64
+            .decode(signature)
+            .ok()
+            .and_then(|bytes| Signature::from_slice(&bytes).ok())
+            .context(format!("Malformed signature by {}", public_key_text(&key)))?;
+
+        if !trusted.contains(&key) {
+            signers.push(public_key_text(&key));
+            continue;
+        }
+        return key
+            .verify(content.as_bytes(), &signature)
+            .context(format!("The signature by {} doesn't match the list, it may have been tampered with", public_key_text(&key)));
+    }
+
+    if signers.is_empty() {
+        anyhow::bail!("The mod list is not signed");
+    }
+    anyhow::bail!("The mod list is only signed by keys that aren't trusted: {}", signers.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST: &str = "# pack\nREQUIRED | sodium.jar | https://cdn.modrinth.com/sodium.jar\n";
+
+    fn key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn embedded_signature_verifies() {
+        let signed = sign_embedded(LIST, &[key(1)]);
+        assert!(signed.starts_with(LIST));
+        verify(&signed, None, &[key(1).verifying_key()]).unwrap();
+    }
+
+    #[test]
+    fn tampered_list_is_refused() {
+        let signed = sign_embedded(LIST, &[key(1)]);
+        let tampered = signed.replace("sodium.jar |", "sodium.jar.exe |");
+        assert!(verify(&tampered, None, &[key(1).verifying_key()]).is_err());
+
+        let appended = format!("{}REQUIRED | evil.jar | https://example.com/evil.jar\n", signed);
+        assert!(verify(&appended, None, &[key(1).verifying_key()]).is_err());
+    }
+
+    #[test]
+    fn untrusted_key_is_refused() {
+        let signed = sign_embedded(LIST, &[key(2)]);
+        let error = verify(&signed, None, &[key(1).verifying_key()]).unwrap_err();
+        assert!(error.to_string().contains("aren't trusted"), "{}", error);
+    }
+
+    #[test]
+    fn unsigned_list_is_refused() {
+        let error = verify(LIST, None, &[key(1).verifying_key()]).unwrap_err();
+        assert!(error.to_string().contains("not signed"), "{}", error);
+    }
+
+    #[test]
+    fn detached_signature_verifies() {
+        let detached = signature_lines(LIST, &[key(1)]);
+        verify(LIST, Some(&detached), &[key(1).verifying_key()]).unwrap();
+        assert!(verify("# other\n", Some(&detached), &[key(1).verifying_key()]).is_err());
+    }
+
+    #[test]
+    fn embedded_signature_takes_precedence() {
+        let signed = sign_embedded(LIST, &[key(1)]);
+        let detached = signature_lines(LIST, &[key(2)]);
+        verify(&signed, Some(&detached), &[key(1).verifying_key()]).unwrap();
+        assert!(verify(&signed, Some(&detached), &[key(2).verifying_key()]).is_err());
+    }
+
+    #[test]
+    fn rotation_signs_for_old_and_new_keys() {
+        let signed = sign_embedded(LIST, &[key(2), key(1)]);
+        verify(&signed, None, &[key(1).verifying_key()]).unwrap();
+        verify(&signed, None, &[key(2).verifying_key()]).unwrap();
+        assert!(verify(&signed, None, &[key(3).verifying_key()]).is_err());
+    }
+
+    #[test]
+    fn signing_again_replaces_the_signature() {
+        let signed = sign_embedded(LIST, &[key(1)]);
+        assert_eq!(sign_embedded(&signed, &[key(1)]), signed);
+
+        let resigned = sign_embedded(&signed, &[key(2)]);
+        assert_eq!(resigned.matches(SIGNATURE_PREFIX).count(), 1);
+        verify(&resigned, None, &[key(2).verifying_key()]).unwrap();
+    }
+
+    #[test]
+    fn missing_final_newline_is_signed_as_if_present() {
+        let signed = sign_embedded(LIST.trim_end(), &[key(1)]);
+        verify(&signed, None, &[key(1).verifying_key()]).unwrap();
+        verify(LIST.trim_end(), Some(&signature_lines(LIST, &[key(1)])), &[key(1).verifying_key()]).unwrap();
+    }
+
+    #[test]
+    fn public_keys_round_trip() {
+        let public = key(1).verifying_key();
+        assert_eq!(parse_public_key(&public_key_text(&public)).unwrap(), public);
+        assert!(parse_public_key("not a key").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::modmanager::STATE_DIR;
+use crate::types::ModEntry;
+
+/// Records what ModSync installed, inside STATE_DIR
+pub const STATE_FILE: &str = "state.json";
+
+const STATE_VERSION: u32 = 1;
+
+/// Everything ModSync installed in an instance, keyed by path relative to the instance root.
+/// Files that are not in here were added by the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceState {
+    pub version: u32,
+    pub files: BTreeMap<String, InstalledFile>,
+}
+
+/// A file as ModSync left it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledFile {
+    /// As written in the list (`<sha256>` or `<algorithm>:<hex>`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub size: u64,
+    pub mtime_ns: u64, // modification time, nanoseconds since the Unix epoch
+    pub url: String,
+}
+
+impl Default for InstanceState {
+    fn default() -> Self {
+        Self { version: STATE_VERSION, files: BTreeMap::new() }
+    }
+}
+
+impl InstalledFile {
+    /// Reads size and mtime of a file that was just installed or verified
+    fn from_disk(entry: &ModEntry, path: &Path, url: String) -> Result<Self> {
+        let (size, mtime_ns) = disk_stamp(path)?;
+        Ok(Self {
+            hash: entry.hash.as_ref().map(|h| h.to_string()),
+            size,
+            mtime_ns,
+            url,
+        })
+    }
+
+    /// Returns true if the file on disk still has the recorded size and mtime
+    pub fn matches_disk(&self, path: &Path) -> bool {
+        disk_stamp(path).is_ok_and(|stamp| stamp == (self.size, self.mtime_ns))
+    }
+}
+
+impl InstanceState {
+    /// Loads `.modsync/state.json`. A missing or unreadable state starts fresh,
+    /// which only means nothing is known to be installed by ModSync yet.
+    pub fn load(mods_dir: &Path) -> Self {
+        let path = mods_dir.join(STATE_DIR).join(STATE_FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", path.display(), e);
+                return Self::default();
+            }
+        };
+
+        match serde_json::from_str::<Self>(&text) {
+            Ok(state) if state.version <= STATE_VERSION => state,
+            Ok(state) => {
+                eprintln!("Ignoring {} written by a newer ModSync (version {})", path.display(), state.version);
+                Self::default()
+            }
+            Err(e) => {
+                eprintln!("Ignoring broken {}: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Writes the state through a temporary file, so a crash never leaves half a file behind
+    pub fn save(&self, mods_dir: &Path) -> Result<()> {
+        let dir = mods_dir.join(STATE_DIR);
+        fs::create_dir_all(&dir).context("Failed to create .modsync folder")?;
+
+        let path = dir.join(STATE_FILE);
+        let tmp = dir.join(format!("{}.tmp", STATE_FILE));
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize state")?;
+        fs::write(&tmp, json).context(format!("Failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).context(format!("Failed to write {}", path.display()))
+    }
+
+    /// Remembers a file that was installed or verified during this sync. `source` is the URL
+    /// (or mirror) it was downloaded from; files that weren't downloaded keep the recorded one.
+    pub fn record(&mut self, entry: &ModEntry, mods_dir: &Path, source: Option<&str>) -> Result<()> {
+        let url = source
+            .map(str::to_string)
+            .or_else(|| self.files.get(&entry.relative_path()).map(|file| file.url.clone()))
+            .unwrap_or_else(|| entry.url.clone());
+        let file = InstalledFile::from_disk(entry, &entry.local_path(mods_dir), url)
+            .context(format!("Failed to read {}", entry.filename))?;
+        self.files.insert(entry.relative_path(), file);
+        Ok(())
+    }
+
+    /// Returns true if the file was verified against the entry's hash before and hasn't changed since
+    pub fn is_verified(&self, entry: &ModEntry, path: &Path) -> bool {
+        let (Some(expected), Some(file)) = (&entry.hash, self.files.get(&entry.relative_path())) else {
+            return false;
+        };
+        file.hash.as_ref().is_some_and(|hash| *hash == expected.to_string()) && file.matches_disk(path)
+    }
+
+    /// Returns true if ModSync installed the file and it was changed on disk afterwards
+    pub fn is_modified(&self, entry: &ModEntry, path: &Path) -> bool {
+        self.files.get(&entry.relative_path()).is_some_and(|file| !file.matches_disk(path))
+    }
+
+    pub fn forget(&mut self, relative_path: &str) {
+        self.files.remove(relative_path);
+    }
+
+    /// Recorded files whose entry is no longer in the list
+    pub fn orphans(&self, entries: &[ModEntry]) -> Vec<(String, InstalledFile)> {
+        let listed: HashSet<String> = entries.iter().map(|e| e.relative_path().to_lowercase()).collect();
+        self.files
+            .iter()
+            .filter(|(path, _)| !listed.contains(&path.to_lowercase()))
+            .map(|(path, file)| (path.clone(), file.clone()))
+            .collect()
+    }
+}
+
+/// Size and modification time (in nanoseconds) of a file
+fn disk_stamp(path: &Path) -> std::io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let mtime = metadata.modified()?.duration_since(UNIX_EPOCH).unwrap_or_default();
+    Ok((metadata.len(), mtime.as_nanos() as u64))
+}
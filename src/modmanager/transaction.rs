@@ -0,0 +1,354 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::modmanager::state::STATE_FILE;
+use crate::modmanager::STATE_DIR;
+use crate::types::ModEntry;
+
+/// Every sync that changed something gets a folder in here, named after its transaction id
+pub const BACKUPS_DIR: &str = "backups";
+
+/// How many transactions are kept for rollback unless configured otherwise
+pub const DEFAULT_KEEP_TRANSACTIONS: u32 = 5;
+
+const TRANSACTION_FILE: &str = "transaction.json";
+
+/// One file a sync created, replaced or removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    /// Path relative to the instance root
+    pub path: String,
+    /// Where the previous file was moved to, relative to the instance root. None if there was no file.
+    pub backup: Option<String>,
+}
+
+/// What is written to `transaction.json`
+#[derive(Debug, Serialize, Deserialize)]
+struct TransactionRecord {
+    id: String,
+    changes: Vec<Change>,
+    /// Contents of state.json before the sync, None if there was none
+    previous_state: Option<String>,
+}
+
+/// Collects the changes of a running sync, so it can be undone with `rollback`.
+/// Nothing is deleted for good: removed and replaced files are moved into the transaction's folder.
+pub struct Transaction {
+    id: String,
+    mods_dir: PathBuf,
+    previous_state: Option<String>,
+    changes: parking_lot::Mutex<Vec<Change>>,
+}
+
+/// What a rollback did
+pub struct RollbackSummary {
+    pub id: String,
+    pub restored: usize,
+    pub deleted: usize,
+}
+
+impl Transaction {
+    /// Starts a transaction; its folder is only created once the first file is backed up
+    pub fn begin(mods_dir: &Path) -> Self {
+        let id = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default()
+            .to_string();
+        Self {
+            id,
+            mods_dir: mods_dir.to_path_buf(),
+            previous_state: fs::read_to_string(mods_dir.join(STATE_DIR).join(STATE_FILE)).ok(),
+            changes: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn dir(&self) -> PathBuf {
+        transaction_dir(&self.mods_dir, &self.id)
+    }
+
+    /// Backup location of a file, relative to the instance root
+    fn backup_path(&self, entry: &ModEntry) -> String {
+        format!("{}/{}/{}/files/{}", STATE_DIR, BACKUPS_DIR, self.id, entry.relative_path())
+    }
+
+    /// Moves a file into the backup instead of deleting it
+    pub fn remove(&self, entry: &ModEntry) -> Result<()> {
+        let backup = self.backup_path(entry);
+        let target = self.mods_dir.join(&backup);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context("Failed to create backup folder")?;
+        }
+        fs::rename(entry.local_path(&self.mods_dir), &target)
+            .context(format!("Failed to remove {}", entry.filename))?;
+        self.record(entry, Some(backup));
+        Ok(())
+    }
+
+    /// Keeps the current version of a file that is about to be replaced.
+    /// Hard-linked where possible, so the backup costs no space. Returns the backup location.
+    pub fn backup(&self, entry: &ModEntry) -> Result<String> {
+        let backup = self.backup_path(entry);
+        let target = self.mods_dir.join(&backup);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context("Failed to create backup folder")?;
+        }
+        let source = entry.local_path(&self.mods_dir);
+        if fs::hard_link(&source, &target).is_err() {
+            fs::copy(&source, &target).context(format!("Failed to back up {}", entry.filename))?;
+        }
+        Ok(backup)
+    }
+
+    /// Drops a backup made with `backup` when the file ended up not being replaced
+    pub fn discard(&self, backup: &str) {
+        let _ = fs::remove_file(self.mods_dir.join(backup));
+    }
+
+    /// Records a file that was replaced, after `backup` saved the previous version
+    pub fn record_replaced(&self, entry: &ModEntry, backup: String) {
+        self.record(entry, Some(backup));
+    }
+
+    /// Records a file that didn't exist before, so rollback deletes it
+    pub fn record_added(&self, entry: &ModEntry) {
+        self.record(entry, None);
+    }
+
+    /// Records a file that was moved somewhere else in the instance (quarantine)
+    pub fn record_moved(&self, entry: &ModEntry, target: &Path) {
+        let backup = target
+            .strip_prefix(&self.mods_dir)
+            .unwrap_or(target)
+            .to_string_lossy()
+            .replace('\\', "/");
+        self.record(entry, Some(backup));
+    }
+
+    fn record(&self, entry: &ModEntry, backup: Option<String>) {
+        self.changes.lock().push(Change { path: entry.relative_path(), backup });
+    }
+
+    /// Writes the transaction record and drops the oldest transactions beyond `keep`.
+    /// Returns false if nothing changed, in which case nothing is kept.
+    pub fn commit(&self, keep: usize) -> Result<bool> {
+        let changes = self.changes.lock().clone();
+        if changes.is_empty() {
+            let _ = fs::remove_dir_all(self.dir());
+            return Ok(false);
+        }
+
+        let record = TransactionRecord {
+            id: self.id.clone(),
+            changes,
+            previous_state: self.previous_state.clone(),
+        };
+        let dir = self.dir();
+        fs::create_dir_all(&dir).context("Failed to create backup folder")?;
+        // Only a complete record makes the transaction visible to `list`
+        let tmp = dir.join(format!("{}.tmp", TRANSACTION_FILE));
+        let json = serde_json::to_string_pretty(&record).context("Failed to serialize transaction")?;
+        fs::write(&tmp, json).context("Failed to write transaction record")?;
+        fs::rename(&tmp, dir.join(TRANSACTION_FILE)).context("Failed to write transaction record")?;
+
+        let ids = list(&self.mods_dir)?;
+        for old in ids.iter().take(ids.len().saturating_sub(keep)) {
+            fs::remove_dir_all(transaction_dir(&self.mods_dir, old))
+                .context(format!("Failed to remove old backup {}", old))?;
+        }
+        Ok(true)
+    }
+}
+
+fn transaction_dir(mods_dir: &Path, id: &str) -> PathBuf {
+    mods_dir.join(STATE_DIR).join(BACKUPS_DIR).join(id)
+}
+
+/// Ids of all kept transactions, oldest first
+pub fn list(mods_dir: &Path) -> Result<Vec<String>> {
+    let dir = match fs::read_dir(mods_dir.join(STATE_DIR).join(BACKUPS_DIR)) {
+        Ok(dir) => dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context("Failed to read backups"),
+    };
+
+    let mut ids: Vec<(u128, String)> = Vec::new();
+    for entry in dir {
+        let entry = entry.context("Failed to read backups")?;
+        let id = entry.file_name().to_string_lossy().to_string();
+        // Folders of unfinished syncs have no record and can't be rolled back
+        if let Ok(number) = id.parse() && entry.path().join(TRANSACTION_FILE).is_file() {
+            ids.push((number, id));
+        }
+    }
+    ids.sort();
+    Ok(ids.into_iter().map(|(_, id)| id).collect())
+}
+
+/// Undoes the latest transaction, restoring every file it removed or replaced and deleting
+/// every file it added. Older transactions can only be undone after the ones that followed them.
+pub fn rollback(mods_dir: &Path, id: Option<&str>) -> Result<RollbackSummary> {
+    let ids = list(mods_dir)?;
+    let Some(latest) = ids.last() else {
+        anyhow::bail!("There is no sync to roll back");
+    };
+    if let Some(id) = id
+        && id != latest
+    {
+        anyhow::bail!("Only the latest sync ({}) can be rolled back, not {}", latest, id);
+    }
+
+    let dir = transaction_dir(mods_dir, latest);
+    let text = fs::read_to_string(dir.join(TRANSACTION_FILE)).context("Failed to read transaction record")?;
+    let record: TransactionRecord = serde_json::from_str(&text).context("Broken transaction record")?;
+
+    let mut summary = RollbackSummary { id: record.id.clone(), restored: 0, deleted: 0 };
+    for change in record.changes.iter().rev() {
+        let target = mods_dir.join(&change.path);
+        match &change.backup {
+            Some(backup) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).context(format!("Failed to restore {}", change.path))?;
+                }
+                fs::rename(mods_dir.join(backup), &target).context(format!("Failed to restore {}", change.path))?;
+                summary.restored += 1;
+            }
+            None => {
+                if target.exists() {
+                    fs::remove_file(&target).context(format!("Failed to delete {}", change.path))?;
+                }
+                summary.deleted += 1;
+            }
+        }
+    }
+
+    let state_path = mods_dir.join(STATE_DIR).join(STATE_FILE);
+    match &record.previous_state {
+        Some(state) => fs::write(&state_path, state).context("Failed to restore install state")?,
+        None => {
+            let _ = fs::remove_file(&state_path);
+        }
+    }
+
+    fs::remove_dir_all(&dir).context("Failed to remove rolled back transaction")?;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modmanager::exclusive;
+
+    fn write(mods_dir: &Path, path: &str, contents: &str) {
+        let path = mods_dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(mods_dir: &Path, path: &str) -> Option<String> {
+        fs::read_to_string(mods_dir.join(path)).ok()
+    }
+
+    /// Transaction ids are milliseconds, two in a row must not collide
+    fn begin(mods_dir: &Path) -> Transaction {
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        Transaction::begin(mods_dir)
+    }
+
+    #[test]
+    fn rollback_restores_every_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods_dir = dir.path();
+        let state_path = format!("{}/{}", STATE_DIR, STATE_FILE);
+        write(mods_dir, "mods/old.jar", "old");
+        write(mods_dir, "mods/updated.jar", "v1");
+        write(mods_dir, "mods/extra.jar", "extra");
+        write(mods_dir, &state_path, "state before");
+
+        let transaction = begin(mods_dir);
+        let removed = ModEntry::unlisted("old.jar".to_string());
+        transaction.remove(&removed).unwrap();
+
+        let updated = ModEntry::unlisted("updated.jar".to_string());
+        let backup = transaction.backup(&updated).unwrap();
+        // Replaced the way a download is, by moving a new file over the old one
+        write(mods_dir, "mods/updated.jar.part", "v2");
+        fs::rename(mods_dir.join("mods/updated.jar.part"), mods_dir.join("mods/updated.jar")).unwrap();
+        transaction.record_replaced(&updated, backup);
+
+        let added = ModEntry::unlisted("new.jar".to_string());
+        write(mods_dir, "mods/new.jar", "new");
+        transaction.record_added(&added);
+
+        let extra = ModEntry::unlisted("extra.jar".to_string());
+        let target = exclusive::quarantine(&extra, mods_dir, &exclusive::quarantine_dir(mods_dir)).unwrap();
+        transaction.record_moved(&extra, &target);
+
+        write(mods_dir, &state_path, "state after");
+        assert!(transaction.commit(DEFAULT_KEEP_TRANSACTIONS as usize).unwrap());
+        assert_eq!(read(mods_dir, "mods/old.jar"), None);
+        assert_eq!(read(mods_dir, "mods/updated.jar").as_deref(), Some("v2"));
+        assert_eq!(read(mods_dir, "mods/extra.jar"), None);
+
+        let summary = rollback(mods_dir, None).unwrap();
+        assert_eq!(summary.id, transaction.id());
+        assert_eq!((summary.restored, summary.deleted), (3, 1));
+        assert_eq!(read(mods_dir, "mods/old.jar").as_deref(), Some("old"));
+        assert_eq!(read(mods_dir, "mods/updated.jar").as_deref(), Some("v1"));
+        assert_eq!(read(mods_dir, "mods/new.jar"), None);
+        assert_eq!(read(mods_dir, "mods/extra.jar").as_deref(), Some("extra"));
+        assert!(!target.exists());
+        assert_eq!(read(mods_dir, &state_path).as_deref(), Some("state before"));
+        assert!(list(mods_dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rollback_removes_state_that_did_not_exist_before() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods_dir = dir.path();
+        let transaction = begin(mods_dir);
+        write(mods_dir, "mods/new.jar", "new");
+        transaction.record_added(&ModEntry::unlisted("new.jar".to_string()));
+        write(mods_dir, &format!("{}/{}", STATE_DIR, STATE_FILE), "state after");
+        transaction.commit(1).unwrap();
+
+        rollback(mods_dir, None).unwrap();
+        assert_eq!(read(mods_dir, &format!("{}/{}", STATE_DIR, STATE_FILE)), None);
+        assert_eq!(read(mods_dir, "mods/new.jar"), None);
+    }
+
+    #[test]
+    fn commit_keeps_only_the_latest_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let mods_dir = dir.path();
+        let mut ids = Vec::new();
+        for i in 0..3 {
+            let transaction = begin(mods_dir);
+            write(mods_dir, &format!("mods/{}.jar", i), "new");
+            transaction.record_added(&ModEntry::unlisted(format!("{}.jar", i)));
+            assert!(transaction.commit(2).unwrap());
+            ids.push(transaction.id().to_string());
+        }
+        assert_eq!(list(mods_dir).unwrap(), ids[1..]);
+        assert!(!transaction_dir(mods_dir, &ids[0]).exists());
+
+        // Nothing changed: no transaction is kept and none are pruned
+        assert!(!begin(mods_dir).commit(1).unwrap());
+        assert_eq!(list(mods_dir).unwrap(), ids[1..]);
+
+        // Only the latest can be undone, the older one after it
+        assert!(rollback(mods_dir, Some(&ids[1])).is_err());
+        assert_eq!(rollback(mods_dir, None).unwrap().id, ids[2]);
+        assert_eq!(rollback(mods_dir, None).unwrap().id, ids[1]);
+        assert!(rollback(mods_dir, None).is_err());
+        assert_eq!(read(mods_dir, "mods/0.jar").as_deref(), Some("new"));
+        assert_eq!(read(mods_dir, "mods/1.jar"), None);
+    }
+}
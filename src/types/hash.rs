@@ -0,0 +1,202 @@
+use std::fmt;
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Hash algorithms a mod list may use. Modrinth publishes SHA1 and SHA512, CurseForge SHA1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, clap::ValueEnum)]
+pub enum HashAlgorithm {
+    Sha1,
+    #[default]
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "sha512" => Some(HashAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Prefix used in mod lists (`sha512:...`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// Name for messages, e.g. `SHA512 mismatch`
+    pub fn label(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "SHA1",
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// Length of a hash in hexadecimal characters
+    pub fn hex_len(&self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 => 40,
+            HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha512 => 128,
+        }
+    }
+}
+
+/// Expected hash of a file: `<hex>` for SHA256, or `sha1:<hex>` / `sha512:<hex>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileHash {
+    pub algorithm: HashAlgorithm,
+    pub hex: String, // always lowercase
+}
+
+impl FileHash {
+    pub fn new(algorithm: HashAlgorithm, hex: &str) -> Self {
+        Self { algorithm, hex: hex.to_ascii_lowercase() }
+    }
+
+    /// Parses a hash as written in a mod list. Unprefixed hashes are SHA256, as they always were.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (algorithm, hex) = match value.split_once(':') {
+            Some((prefix, hex)) => {
+                let algorithm = HashAlgorithm::parse(prefix.trim()).ok_or_else(|| {
+                    format!("unknown hash algorithm `{}` (expected sha1, sha256 or sha512)", prefix)
+                })?;
+                (algorithm, hex.trim())
+            }
+            None => (HashAlgorithm::Sha256, value),
+        };
+
+        if hex.len() != algorithm.hex_len() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid {} `{}` (expected {} hexadecimal characters)",
+                algorithm.label(),
+                hex,
+                algorithm.hex_len()
+            ));
+        }
+        Ok(Self::new(algorithm, hex))
+    }
+
+    /// Returns true if `actual` (hex, any case) is this hash
+    pub fn matches(&self, actual: &str) -> bool {
+        self.hex.eq_ignore_ascii_case(actual)
+    }
+}
+
+impl fmt::Display for FileHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.algorithm {
+            HashAlgorithm::Sha256 => f.write_str(&self.hex),
+            algorithm => write!(f, "{}:{}", algorithm.as_str(), self.hex),
+        }
+    }
+}
+
+/// Incremental hasher for any supported algorithm
+#[derive(Clone)]
+pub enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => Hasher::Sha512(Sha512::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+        }
+    }
+
+    /// Lowercase hex digest
+    pub fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha1(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Sha512(h) => format!("{:x}", h.finalize()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_algorithm() {
+        let cases = [
+            ("a".repeat(64), HashAlgorithm::Sha256),
+            (format!("sha256:{}", "a".repeat(64)), HashAlgorithm::Sha256),
+            (format!("sha1:{}", "a".repeat(40)), HashAlgorithm::Sha1),
+            (format!("SHA512:{}", "a".repeat(128)), HashAlgorithm::Sha512),
+            (format!("sha1: {}", "a".repeat(40)), HashAlgorithm::Sha1),
+        ];
+        for (text, algorithm) in cases {
+            let hash = FileHash::parse(&text).unwrap_or_else(|e| panic!("{}: {}", text, e));
+            assert_eq!(hash.algorithm, algorithm, "{}", text);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        let cases = [
+            ("a".repeat(63), "expected 64 hexadecimal characters"),
+            ("a".repeat(65), "expected 64 hexadecimal characters"),
+            ("g".repeat(64), "expected 64 hexadecimal characters"),
+            (format!("sha1:{}", "a".repeat(64)), "expected 40 hexadecimal characters"),
+            (format!("sha512:{}", "a".repeat(64)), "expected 128 hexadecimal characters"),
+            (format!("md5:{}", "a".repeat(32)), "unknown hash algorithm `md5`"),
+            (String::new(), "expected 64 hexadecimal characters"),
+        ];
+        for (text, expected) in cases {
+            let error = FileHash::parse(&text).unwrap_err();
+            assert!(error.contains(expected), "{}: {}", text, error);
+        }
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for text in ["a".repeat(64), format!("sha1:{}", "b".repeat(40)), format!("sha512:{}", "c".repeat(128))] {
+            assert_eq!(FileHash::parse(&text).unwrap().to_string(), text);
+        }
+        // Stored lowercase, written without the default prefix
+        assert_eq!(FileHash::parse(&format!("sha256:{}", "A".repeat(64))).unwrap().to_string(), "a".repeat(64));
+    }
+
+    #[test]
+    fn matches_in_any_case() {
+        let hash = FileHash::parse(&format!("sha1:{}", "ab".repeat(20))).unwrap();
+        assert!(hash.matches(&"AB".repeat(20)));
+        assert!(!hash.matches(&"ac".repeat(20)));
+    }
+
+    #[test]
+    fn hashers_produce_known_digests() {
+        let digest = |algorithm| {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(b"ab");
+            hasher.update(b"c");
+            hasher.finalize_hex()
+        };
+        assert_eq!(digest(HashAlgorithm::Sha1), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(digest(HashAlgorithm::Sha256), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(digest(HashAlgorithm::Sha512).starts_with("ddaf35a193617aba"));
+    }
+}
@@ -0,0 +1,263 @@
+use serde::{Deserialize, Serialize};
+
+use crate::types::{build_entry, check_conflicts, Env, ListDiagnostic, ModEntry, ModList, RawEntry};
+
+/// Highest manifest `format_version` this build understands
+pub const MANIFEST_FORMAT_VERSION: u32 = 1;
+
+/// Pack header of a TOML manifest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PackInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_modsync_version: Option<String>,
+}
+
+/// One `[[entries]]` table, with the same meaning as the pipe format columns
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub category: String,
+    pub filename: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+    /// Fallback URLs, tried in order when `url` fails
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    /// `<sha256>`, `sha1:<hex>` or `sha512:<hex>`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub env: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    /// Size in bytes, only used to show download progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Versioned mod list format:
+///
+/// ```toml
+/// format_version = 1
+///
+/// [pack]
+/// name = "Our Pack"
+/// version = "1.2.0"
+/// min_modsync_version = "0.1.0"
+///
+/// [[entries]]
+/// category = "REQUIRED"
+/// filename = "fabric-api.jar"
+/// url = "https://example.com/fabric-api.jar"
+/// mirrors = ["https://mirror.example.org/fabric-api.jar"]
+/// hash = "sha512:..."
+/// size = 2154321
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    #[serde(default)]
+    pub pack: PackInfo,
+    #[serde(default)]
+    pub entries: Vec<toml::Spanned<ManifestEntry>>,
+}
+
+impl ManifestEntry {
+    fn from_entry(entry: &ModEntry) -> Self {
+        Self {
+            category: entry.category.clone(),
+            filename: entry.filename.clone(),
+            url: entry.url.clone(),
+            mirrors: entry.mirrors.clone(),
+            hash: entry.hash.as_ref().map(|h| h.to_string()).unwrap_or_default(),
+            env: match entry.env {
+                Env::Both => String::new(),
+                env => env.as_str().to_string(),
+            },
+            name: entry.name.clone().unwrap_or_default(),
+            size: entry.size,
+        }
+    }
+}
+
+/// Returns true if the text is a TOML manifest rather than a pipe-separated list.
+/// Every pipe list entry contains `|`, so the first meaningful line decides.
+pub fn is_manifest(text: &str) -> bool {
+    text.lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .is_some_and(|l| !l.contains('|'))
+}
+
+/// Parses either list format
+pub fn parse_any(text: &str) -> ModList {
+    if is_manifest(text) {
+        parse_manifest(text)
+    } else {
+        crate::types::parse_mod_list(text)
+    }
+}
+
+/// Parses a TOML manifest, reporting problems with the line they occur on
+pub fn parse_manifest(text: &str) -> ModList {
+    let mut list = ModList::default();
+
+    let manifest: Manifest = match toml::from_str(text) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            let line = e.span().map(|span| line_of(text, span.start)).unwrap_or(1);
+            list.diagnostics.push(ListDiagnostic::error(line, format!("invalid manifest: {}", e.message())));
+            return list;
+        }
+    };
+
+    if manifest.format_version > MANIFEST_FORMAT_VERSION {
+        list.diagnostics.push(ListDiagnostic::error(
+            line_of(text, text.find("format_version").unwrap_or(0)),
+            format!(
+                "manifest format version {} is not supported (newest supported: {}), update ModSync",
+                manifest.format_version, MANIFEST_FORMAT_VERSION
+            ),
+        ));
+        return list;
+    }
+
+    if let Some(required) = &manifest.pack.min_modsync_version {
+        let running = env!("CARGO_PKG_VERSION");
+        if !version_at_least(running, required) {
+            list.diagnostics.push(ListDiagnostic::error(
+                line_of(text, text.find("min_modsync_version").unwrap_or(0)),
+                format!("this pack requires ModSync {} or newer (running {})", required, running),
+            ));
+        }
+    }
+
+    for spanned in &manifest.entries {
+        let line = line_of(text, spanned.span().start);
+        let entry = spanned.get_ref();
+        let raw = RawEntry {
+            category: &entry.category,
+            filename: &entry.filename,
+            url: &entry.url,
+            mirrors: entry.mirrors.iter().map(String::as_str).collect(),
+            hash: &entry.hash,
+            env: &entry.env,
+            name: &entry.name,
+            size: entry.size,
+        };
+        if let Some(entry) = build_entry(raw, line, &mut list.diagnostics) {
+            list.entries.push(entry);
+        }
+    }
+
+    check_conflicts(&mut list);
+    list.pack = Some(manifest.pack);
+    list
+}
+
+/// Serializes a parsed list as a TOML manifest. Comments of the original list are not kept.
+pub fn to_manifest(list: &ModList, pack: PackInfo) -> anyhow::Result<String> {
+    let manifest = Manifest {
+        format_version: MANIFEST_FORMAT_VERSION,
+        pack,
+        entries: list.entries
+            .iter()
+            .map(|e| toml::Spanned::new(0..0, ManifestEntry::from_entry(e)))
+            .collect(),
+    };
+
+    Ok(toml::to_string(&manifest)?)
+}
+
+/// 1-based line number of a byte offset
+fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// Compares dotted version numbers (`0.10.0` >= `0.9.2`); missing parts count as 0
+fn version_at_least(version: &str, required: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v')
+            .split('.')
+            .map(|part| part.chars().take_while(|c| c.is_ascii_digit()).collect::<String>().parse().unwrap_or(0))
+            .collect()
+    };
+    let (mut version, mut required) = (parse(version), parse(required));
+    let len = version.len().max(required.len());
+    version.resize(len, 0);
+    required.resize(len, 0);
+    version >= required
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::parse_mod_list;
+
+    /// Everything an entry says, in a comparable form
+    fn fields(list: &ModList) -> Vec<String> {
+        list.entries
+            .iter()
+            .map(|e| format!(
+                "{} {} {} {:?} {:?} {:?} {:?} {:?}",
+                e.category,
+                e.filename,
+                e.url,
+                e.mirrors,
+                e.hash.as_ref().map(|h| h.to_string()),
+                e.env,
+                e.name,
+                e.size
+            ))
+            .collect()
+    }
+
+    #[test]
+    fn converting_to_a_manifest_is_lossless() {
+        let sha256 = "a".repeat(64);
+        let sha1 = format!("sha1:{}", "b".repeat(40));
+        let sha512 = format!("sha512:{}", "c".repeat(128));
+        let text = [
+            "# pack".to_string(),
+            format!(
+                "REQUIRED | fabric-api.jar | https://cdn.modrinth.com/a.jar https://github.com/m/a.jar | {} | both | Fabric API",
+                sha256
+            ),
+            format!("REQUIRED | sodium.jar | https://cdn.modrinth.com/s.jar | {} | client | Sodium", sha1),
+            format!("REQUIRED | config/x.json | https://raw.githubusercontent.com/x.json | {} | server |", sha512),
+            "REQUIRED | resourcepacks/pack.zip | https://cdn.modrinth.com/p.zip | | |".to_string(),
+            "REMOVE | old-mod.jar |".to_string(),
+            "REMOVE | old-shader.jar | | | client | Old Shader".to_string(),
+        ]
+        .join("\n");
+        let list = parse_mod_list(&text);
+        assert!(list.diagnostics.is_empty(), "{:?}", list.diagnostics);
+
+        let pack = PackInfo { name: Some("Pack".to_string()), version: Some("1.0.0".to_string()), min_modsync_version: None };
+        let manifest = to_manifest(&list, pack).unwrap();
+        assert!(is_manifest(&manifest));
+        assert!(manifest.contains(&format!("hash = \"{}\"", sha1)), "{}", manifest);
+
+        let parsed = parse_any(&manifest);
+        assert!(parsed.diagnostics.is_empty(), "{:?}", parsed.diagnostics);
+        assert_eq!(fields(&parsed), fields(&list));
+        assert_eq!(parsed.pack.as_ref().and_then(|p| p.name.as_deref()), Some("Pack"));
+
+        // Converting the converted list again changes nothing
+        let again = to_manifest(&parsed, parsed.pack.clone().unwrap()).unwrap();
+        assert_eq!(again, manifest);
+    }
+
+    #[test]
+    fn manifest_errors_have_line_numbers() {
+        let text = "format_version = 1\n\n[[entries]]\ncategory = \"REQUIRED\"\nfilename = \"a.jar\"\nurl = \"not a url\"\n";
+        let list = parse_manifest(text);
+        assert_eq!(list.errors().map(|d| d.line).collect::<Vec<_>>(), [3]);
+
+        let list = parse_manifest("format_version = 99\n");
+        assert!(list.has_errors());
+    }
+}
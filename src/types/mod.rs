@@ -1,2 +1,7 @@
+#[allow(clippy::module_inception)]
 pub mod types;
-pub use types::*;
\ No newline at end of file
+pub mod hash;
+pub mod manifest;
+pub use types::*;
+pub use hash::*;
+pub use manifest::*;
\ No newline at end of file
@@ -1,42 +1,740 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
 
+use crate::types::FileHash;
+
+pub const CATEGORY_REQUIRED: &str = "REQUIRED";
+pub const CATEGORY_REMOVE: &str = "REMOVE";
+
+/// Folder used for entries that don't name one (`example.jar` -> `mods/example.jar`)
+pub const DEFAULT_TARGET_DIR: &str = "mods";
+
+/// Top-level instance folders entries may be placed in
+pub const TARGET_DIRS: &[&str] = &[
+    "mods",
+    "resourcepacks",
+    "shaderpacks",
+    "config",
+    "defaultconfigs",
+    "datapacks",
+    "kubejs",
+];
+
+/// Longest file or folder name most file systems can store
+const MAX_NAME_LEN: usize = 255;
+
+/// Characters Windows doesn't allow in file names (`:` would also allow drive letters)
+const FORBIDDEN_CHARS: &str = "<>:\"|?*";
+
+/// Device names Windows reserves in every folder, with or without extension
+const RESERVED_NAMES: &[&str] = &["CON", "PRN", "AUX", "NUL"];
+
+/// Which side of the game an entry belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Env {
+    Client,
+    Server,
+    #[default]
+    Both,
+}
+
+impl Env {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "" | "both" => Some(Env::Both),
+            "client" => Some(Env::Client),
+            "server" => Some(Env::Server),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Env::Client => "client",
+            Env::Server => "server",
+            Env::Both => "both",
+        }
+    }
+
+    /// Returns true if both envs include a common side
+    pub fn overlaps(&self, other: Env) -> bool {
+        *self == Env::Both || other == Env::Both || *self == other
+    }
+
+    /// Returns true if entries with this env belong on the given side
+    pub fn includes(&self, side: Side) -> bool {
+        matches!(
+            (self, side),
+            (Env::Both, _) | (Env::Client, Side::Client) | (Env::Server, Side::Server)
+        )
+    }
+}
+
+/// The side ModSync is currently running on (selected with --side)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum Side {
+    #[default]
+    Client,
+    Server,
+}
+
 #[derive(Debug, Clone)]
 pub struct ModEntry {
     pub filename: String,
     pub url: String,
-    pub sha256: Option<String>,
-    pub category: String, // "REQUIRED", "Optional", "Shaders", etc.
+    pub mirrors: Vec<String>, // fallback URLs, tried in order when `url` fails
+    pub hash: Option<FileHash>,
+    pub category: String, // "REQUIRED" or "REMOVE"
+    pub env: Env,
+    pub name: Option<String>, // human-readable name shown in the UI
+    pub size: Option<u64>,    // expected size in bytes, for progress when the server sends no Content-Length
+    pub line: usize,      // line in the mod list this entry came from
 }
 
 impl ModEntry {
+    /// REMOVE entry for a file that isn't in the list, so its removal is reported like any other
+    pub fn unlisted(relative_path: String) -> Self {
+        let name = relative_path.rsplit('/').next().map(str::to_string);
+        Self {
+            filename: relative_path,
+            url: String::new(),
+            mirrors: Vec::new(),
+            hash: None,
+            category: CATEGORY_REMOVE.to_string(),
+            env: Env::Both,
+            name,
+            size: None,
+            line: 0,
+        }
+    }
+
+    /// Download URL followed by its mirrors, in the order they are tried
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
+
     /// Returns true if this mod is in the reserved REQUIRED category
     pub fn is_required(&self) -> bool {
-        self.category.eq_ignore_ascii_case("REQUIRED")
+        self.category.eq_ignore_ascii_case(CATEGORY_REQUIRED)
+    }
+
+    /// Returns true if this mod is marked for deletion
+    pub fn is_remove(&self) -> bool {
+        self.category.eq_ignore_ascii_case(CATEGORY_REMOVE)
+    }
+
+    /// Path relative to the instance root, e.g. `mods/example.jar` or `config/sodium.json`
+    pub fn relative_path(&self) -> String {
+        if self.filename.contains('/') {
+            self.filename.clone()
+        } else {
+            format!("{}/{}", DEFAULT_TARGET_DIR, self.filename)
+        }
+    }
+
+    /// Folder the file is placed in, relative to the instance root
+    pub fn target_dir(&self) -> &str {
+        match self.filename.rsplit_once('/') {
+            Some((dir, _)) => dir,
+            None => DEFAULT_TARGET_DIR,
+        }
+    }
+
+    /// File name without the target folder
+    pub fn file_name(&self) -> &str {
+        match self.filename.rsplit_once('/') {
+            Some((_, name)) => name,
+            None => &self.filename,
+        }
     }
 
     pub fn local_path(&self, mods_dir: &Path) -> PathBuf {
-        mods_dir.join("mods").join(&self.filename)
+        mods_dir.join(self.relative_path())
+    }
+
+    /// Like local_path, but fails if the entry would point outside the instance.
+    /// Lists are checked when parsed; this also covers paths read back from `.modsync` files.
+    pub fn checked_local_path(&self, mods_dir: &Path) -> Result<PathBuf, String> {
+        let relative = self.relative_path();
+        check_path(&relative).map_err(|e| format!("refusing to touch `{}`: {}", relative, e))?;
+        Ok(mods_dir.join(relative))
+    }
+
+    /// Name from the list, or one guessed from the file name if the list has none
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => guess_display_name(self.file_name()),
+        }
+    }
+}
+
+/// Guesses a readable name by cutting the file name at the first dash followed by a digit
+/// (`sodium-fabric-0.5.3.jar` -> `sodium-fabric`)
+pub fn guess_display_name(filename: &str) -> String {
+    let parts: Vec<&str> = filename.split('-').collect();
+    let mut result = String::new();
+
+    for (i, part) in parts.iter().enumerate() {
+        // Check if this part starts with a digit, stop here
+        if part.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+            break;
+        }
+
+        // Add the part to result
+        if i > 0 {
+            result.push('-');
+        }
+        result.push_str(part);
+    }
+
+    // Names starting with a digit (`3dskinlayers-1.2.jar`) would end up empty
+    if result.is_empty() {
+        filename.to_string()
+    } else {
+        result
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found while parsing the mod list, tied to its line number
+#[derive(Debug, Clone)]
+pub struct ListDiagnostic {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl ListDiagnostic {
+    pub fn error(line: usize, message: impl Into<String>) -> Self {
+        Self { line, severity: Severity::Error, message: message.into() }
+    }
+
+    pub fn warning(line: usize, message: impl Into<String>) -> Self {
+        Self { line, severity: Severity::Warning, message: message.into() }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for ListDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "line {}: {}: {}", self.line, severity, self.message)
+    }
+}
+
+/// Result of parsing a whole mod list: valid entries plus everything that was wrong with it
+#[derive(Debug, Clone, Default)]
+pub struct ModList {
+    pub entries: Vec<ModEntry>,
+    pub diagnostics: Vec<ListDiagnostic>,
+    pub pack: Option<crate::types::PackInfo>, // only set for TOML manifests
+}
+
+impl ModList {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ListDiagnostic> {
+        self.diagnostics.iter().filter(|d| d.is_error())
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ListDiagnostic> {
+        self.diagnostics.iter().filter(|d| !d.is_error())
     }
 }
 
-/// Parses a line from the modsync config file into a ModEntry
-pub fn parse_line(line: &str) -> Option<ModEntry> {
+/// Parses a complete mod list, collecting diagnostics for every malformed line
+pub fn parse_mod_list(text: &str) -> ModList {
+    let mut list = ModList::default();
+
+    for (i, line) in text.lines().enumerate() {
+        if let Some(entry) = parse_line(line, i + 1, &mut list.diagnostics) {
+            list.entries.push(entry);
+        }
+    }
+
+    check_conflicts(&mut list);
+    list
+}
+
+/// Finds entries that would fight over the same file on the same side. Paths are compared
+/// case-insensitively, since Windows and macOS treat `Sodium.jar` and `sodium.jar` as one file.
+/// Two entries installing the same file, or removing and installing it, are errors; a repeated REMOVE is dropped.
+/// The sync would otherwise delete and download the file again on every launch.
+pub(crate) fn check_conflicts(list: &mut ModList) {
+    let mut seen: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut dropped: HashSet<usize> = HashSet::new();
+
+    for (i, entry) in list.entries.iter().enumerate() {
+        let earlier = seen.entry(entry.relative_path().to_lowercase()).or_default();
+        for &j in earlier.iter() {
+            let first = &list.entries[j];
+            if dropped.contains(&j) || !first.env.overlaps(entry.env) {
+                continue;
+            }
+
+            match (first.is_remove(), entry.is_remove()) {
+                (true, true) => {
+                    list.diagnostics.push(ListDiagnostic::warning(
+                        entry.line,
+                        format!("`{}` is already removed on line {}", entry.filename, first.line),
+                    ));
+                    dropped.insert(i);
+                }
+                (false, false) if first.relative_path() == entry.relative_path() => {
+                    list.diagnostics.push(ListDiagnostic::error(
+                        entry.line,
+                        format!("`{}` is already listed on line {}", entry.filename, first.line),
+                    ));
+                    break;
+                }
+                (false, false) => {
+                    list.diagnostics.push(ListDiagnostic::error(
+                        entry.line,
+                        format!(
+                            "`{}` and `{}` on line {} only differ in case and would be the same file on Windows and macOS",
+                            entry.filename, first.filename, first.line
+                        ),
+                    ));
+                    break;
+                }
+                (remove_first, _) => {
+                    let (remove, required) = if remove_first { (first, entry) } else { (entry, first) };
+                    list.diagnostics.push(ListDiagnostic::error(
+                        entry.line,
+                        format!(
+                            "`{}` is removed on line {} and required on line {}; drop the REMOVE line \
+                             (a REQUIRED entry with a hash replaces an outdated file by itself)",
+                            entry.filename, remove.line, required.line
+                        ),
+                    ));
+                    break;
+                }
+            }
+        }
+        earlier.push(i);
+    }
+
+    let mut index = 0;
+    list.entries.retain(|_| {
+        index += 1;
+        !dropped.contains(&(index - 1))
+    });
+    // Conflicts are found after the per-line checks; keep the report in line order
+    list.diagnostics.sort_by_key(|d| d.line);
+}
+
+/// Parses a line from the modsync config file into a ModEntry.
+/// Returns None for blank lines, comments and lines with errors (reported in `diagnostics`).
+pub fn parse_line(line: &str, line_no: usize, diagnostics: &mut Vec<ListDiagnostic>) -> Option<ModEntry> {
     let line = line.trim();
     if line.is_empty() || line.starts_with('#') {
         return None;
     }
 
-    let parts: Vec<&str> = line.split('|').collect();
-    let category = parts.get(0)?.to_string();
-    let filename = parts.get(1)?.to_string();
-    let url = parts.get(2)?.to_string();
-    let sha256 = parts.get(3).map(|s| s.to_string());
+    let parts: Vec<&str> = line.split('|').map(str::trim).collect();
+    if parts.len() < 3 {
+        diagnostics.push(ListDiagnostic::error(
+            line_no,
+            format!("expected `Category | ModName | DownloadURL | Hash | Env | Name`, found {} column(s)", parts.len()),
+        ));
+        return None;
+    }
+    if parts.len() > 6 {
+        diagnostics.push(ListDiagnostic::warning(
+            line_no,
+            format!("ignoring {} extra column(s)", parts.len() - 6),
+        ));
+    }
+
+    let column = |i: usize| parts.get(i).copied().unwrap_or_default();
+    // Mirrors follow the download URL in the same column, separated by spaces
+    let mut urls = parts[2].split_whitespace();
+    build_entry(
+        RawEntry {
+            category: parts[0],
+            filename: parts[1],
+            url: urls.next().unwrap_or_default(),
+            mirrors: urls.collect(),
+            hash: column(3),
+            env: column(4),
+            name: column(5),
+            size: None,
+        },
+        line_no,
+        diagnostics,
+    )
+}
+
+/// Unvalidated entry fields as written in the list; empty strings mean "not set"
+pub struct RawEntry<'a> {
+    pub category: &'a str,
+    pub filename: &'a str,
+    pub url: &'a str,
+    pub mirrors: Vec<&'a str>,
+    pub hash: &'a str,
+    pub env: &'a str,
+    pub name: &'a str,
+    pub size: Option<u64>,
+}
+
+/// Validates the fields of one entry. Returns None if any error was reported in `diagnostics`.
+pub fn build_entry(raw: RawEntry<'_>, line_no: usize, diagnostics: &mut Vec<ListDiagnostic>) -> Option<ModEntry> {
+    let errors_before = diagnostics.len();
+
+    let category = raw.category.to_ascii_uppercase();
+    if category != CATEGORY_REQUIRED && category != CATEGORY_REMOVE {
+        diagnostics.push(ListDiagnostic::error(
+            line_no,
+            format!("unknown category `{}` (expected REQUIRED or REMOVE)", raw.category),
+        ));
+    }
+
+    // Windows-style separators mean the same folders
+    let filename = raw.filename.replace('\\', "/");
+    if filename.is_empty() {
+        diagnostics.push(ListDiagnostic::error(line_no, "missing mod filename"));
+    } else if filename.ends_with('/') {
+        diagnostics.push(ListDiagnostic::error(line_no, format!("`{}` does not name a file", raw.filename)));
+    } else if let Err(e) = check_path(&filename) {
+        diagnostics.push(ListDiagnostic::error(line_no, format!("unsafe file name `{}`: {}", raw.filename, e)));
+    } else if let Some((top_dir, _)) = filename.split_once('/')
+        && !TARGET_DIRS.contains(&top_dir)
+    {
+        diagnostics.push(ListDiagnostic::error(
+            line_no,
+            format!("unknown target folder `{}` (expected one of: {})", top_dir, TARGET_DIRS.join(", ")),
+        ));
+    }
 
-    Some(ModEntry {
+    let env = Env::parse(raw.env).unwrap_or_else(|| {
+        diagnostics.push(ListDiagnostic::error(
+            line_no,
+            format!("unknown env `{}` (expected client, server or both)", raw.env),
+        ));
+        Env::Both
+    });
+
+    let hash = Some(raw.hash).filter(|h| !h.is_empty()).and_then(|h| match FileHash::parse(h) {
+        Ok(hash) => Some(hash),
+        Err(e) => {
+            // Only downloaded entries are checked, the others ignore the column
+            if category == CATEGORY_REQUIRED {
+                diagnostics.push(ListDiagnostic::error(line_no, e));
+            }
+            None
+        }
+    });
+
+    let mut entry = ModEntry {
         filename,
-        url,
-        sha256,
+        url: raw.url.to_string(),
+        mirrors: raw.mirrors.iter().map(|m| m.to_string()).collect(),
+        hash,
         category,
-    })
+        env,
+        name: Some(raw.name.to_string()).filter(|s| !s.is_empty()),
+        size: raw.size,
+        line: line_no,
+    };
+
+    // URL and hash only matter for entries that get downloaded
+    if entry.is_required() {
+        if entry.url.is_empty() {
+            diagnostics.push(ListDiagnostic::error(line_no, "missing download URL"));
+        } else if let Err(e) = validate_url(&entry.url) {
+            diagnostics.push(ListDiagnostic::error(line_no, format!("invalid download URL `{}`: {}", entry.url, e)));
+        }
+        for mirror in &entry.mirrors {
+            if let Err(e) = validate_url(mirror) {
+                diagnostics.push(ListDiagnostic::error(line_no, format!("invalid mirror URL `{}`: {}", mirror, e)));
+            }
+        }
+    } else {
+        entry.hash = None;
+        entry.mirrors.clear();
+    }
+
+    if diagnostics[errors_before..].iter().any(|d| d.is_error()) {
+        return None;
+    }
+
+    Some(entry)
+}
+
+/// Checks that a path from a list can only name a file inside the instance: relative, no `.`, `..`
+/// or empty parts, and nothing Windows reserves or can't store
+pub fn check_path(path: &str) -> Result<(), String> {
+    if path.starts_with('/') {
+        return Err("absolute paths are not allowed".to_string());
+    }
+
+    for part in path.split('/') {
+        if part.is_empty() {
+            return Err("empty path component".to_string());
+        }
+        if part == "." || part == ".." {
+            return Err(format!("`{}` is not allowed in paths", part));
+        }
+        if part.len() > MAX_NAME_LEN {
+            return Err(format!("names are limited to {} bytes", MAX_NAME_LEN));
+        }
+        if let Some(c) = part.chars().find(|c| c.is_control() || FORBIDDEN_CHARS.contains(*c)) {
+            return Err(format!("character {:?} is not allowed", c));
+        }
+        if part.ends_with('.') || part.ends_with(' ') {
+            return Err("names can't end with a dot or a space".to_string());
+        }
+
+        // `nul.jar` is as reserved as `nul`
+        let stem = part.split('.').next().unwrap_or_default().trim_end().to_ascii_uppercase();
+        let numbered = ["COM", "LPT"].iter().any(|prefix| {
+            stem.strip_prefix(prefix).is_some_and(|n| n.len() == 1 && n.chars().all(|c| c.is_ascii_digit()))
+        });
+        if RESERVED_NAMES.contains(&stem.as_str()) || numbered {
+            return Err(format!("`{}` is a reserved name on Windows", part));
+        }
+    }
+
+    if path.ends_with(".part") || path.ends_with(".part.validator") {
+        return Err("`.part` files are unfinished downloads".to_string());
+    }
+    Ok(())
+}
+
+fn validate_url(url: &str) -> Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| e.to_string())?;
+    match parsed.scheme() {
+        "http" | "https" => Ok(()),
+        other => Err(format!("unsupported scheme `{}`", other)),
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const URL: &str = "https://cdn.modrinth.com/data/x/a.jar";
+
+    fn parse(lines: &[&str]) -> ModList {
+        parse_mod_list(&lines.join("\n"))
+    }
+
+    fn required(path: &str) -> String {
+        format!("REQUIRED | {} | {}", path, URL)
+    }
+
+    /// Messages of the errors in a list
+    fn errors(list: &ModList) -> Vec<String> {
+        list.errors().map(|d| d.message.clone()).collect()
+    }
+
+    #[test]
+    fn rejects_parent_components() {
+        assert!(check_path("../options.txt").is_err());
+        assert!(check_path("mods/../../evil.jar").is_err());
+        assert!(check_path("config/./a.json").is_err());
+        assert!(check_path("mods//a.jar").is_err());
+    }
+
+    #[test]
+    fn rejects_absolute_paths() {
+        assert!(check_path("/etc/passwd").is_err());
+        assert!(check_path("C:/Windows/evil.dll").is_err());
+
+        let list = parse(&[&required("/home/user/.bashrc")]);
+        assert!(list.has_errors());
+        assert!(list.entries.is_empty());
+    }
+
+    #[test]
+    fn backslashes_are_separators() {
+        let list = parse(&[&required("..\\..\\evil.jar"), &required("config\\sodium.json")]);
+        assert_eq!(errors(&list).len(), 1, "{:?}", errors(&list));
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.entries[0].relative_path(), "config/sodium.json");
+    }
+
+    #[test]
+    fn rejects_reserved_names() {
+        for name in ["nul.jar", "NUL", "con.txt", "COM1", "com1.jar", "lpt9.zip", "mods/aux.jar"] {
+            assert!(check_path(name).is_err(), "{} was accepted", name);
+        }
+        for name in ["com10.jar", "console.jar", "nullable.jar"] {
+            assert!(check_path(name).is_ok(), "{} was rejected", name);
+        }
+    }
+
+    #[test]
+    fn rejects_trailing_dots_and_spaces() {
+        assert!(check_path("a.jar.").is_err());
+        assert!(check_path("a.jar ").is_err());
+        assert!(check_path("config. /a.json").is_err());
+    }
+
+    #[test]
+    fn rejects_forbidden_and_control_characters() {
+        assert!(check_path("a?.jar").is_err());
+        assert!(check_path("a\u{7}.jar").is_err());
+        assert!(check_path(&"a".repeat(MAX_NAME_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn rejects_unfinished_download_names() {
+        assert!(check_path("a.jar.part").is_err());
+        assert!(check_path("a.jar.part.validator").is_err());
+        assert!(check_path("a.part.jar").is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_target_folders() {
+        let list = parse(&[&required("saves/world/level.dat"), &required("shaderpacks/bsl.zip")]);
+        assert_eq!(errors(&list).len(), 1, "{:?}", errors(&list));
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.entries[0].relative_path(), "shaderpacks/bsl.zip");
+    }
+
+    #[test]
+    fn accepts_plain_names_in_the_mods_folder() {
+        let list = parse(&[&required("sodium.jar")]);
+        assert!(!list.has_errors(), "{:?}", errors(&list));
+        assert_eq!(list.entries[0].relative_path(), "mods/sodium.jar");
+    }
+
+    #[test]
+    fn rejects_names_that_only_differ_in_case() {
+        let list = parse(&[&required("Sodium.jar"), &required("sodium.jar")]);
+        let errors = errors(&list);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("only differ in case"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn rejects_the_same_file_twice() {
+        let list = parse(&[&required("sodium.jar"), &required("mods/sodium.jar")]);
+        let errors = errors(&list);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("already listed on line 1"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn sides_do_not_conflict() {
+        let list = parse(&[
+            &format!("{} | | client", required("sodium.jar")),
+            &format!("{} | | server", required("sodium.jar")),
+        ]);
+        assert!(!list.has_errors(), "{:?}", errors(&list));
+    }
+
+    #[test]
+    fn rejects_removing_a_required_file() {
+        let list = parse(&["REMOVE | a.jar |", &required("a.jar")]);
+        let errors = errors(&list);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("removed on line 1 and required on line 2"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn drops_repeated_removals() {
+        let list = parse(&["REMOVE | a.jar |", "REMOVE | A.jar |"]);
+        assert!(!list.has_errors());
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.warnings().count(), 1);
+    }
+
+    #[test]
+    fn diagnostics_are_in_line_order() {
+        let list = parse(&[&required("a.jar"), &required("a.jar"), "REQUIRED | b.jar |"]);
+        let lines: Vec<usize> = list.diagnostics.iter().map(|d| d.line).collect();
+        assert_eq!(lines, [2, 3]);
+    }
+
+    #[test]
+    fn trims_padded_columns() {
+        // As written in the README, with the columns aligned
+        let list = parse(&["REMOVE   | old-mod.jar |", "  REQUIRED |  sodium.jar  |  https://cdn.modrinth.com/s.jar  "]);
+        assert!(list.diagnostics.is_empty(), "{:?}", list.diagnostics);
+        assert_eq!(list.entries[0].filename, "old-mod.jar");
+        assert!(list.entries[0].is_remove());
+        assert_eq!(list.entries[1].filename, "sodium.jar");
+        assert_eq!(list.entries[1].url, "https://cdn.modrinth.com/s.jar");
+    }
+
+    #[test]
+    fn empty_hash_column_means_no_hash() {
+        let list = parse(&[&format!("REQUIRED | a.jar | {} |  | client", URL)]);
+        assert!(list.diagnostics.is_empty(), "{:?}", list.diagnostics);
+        assert_eq!(list.entries[0].hash, None);
+        assert_eq!(list.entries[0].env, Env::Client);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let list = parse(&["# Category | ModName | DownloadURL", "", "   ", &required("a.jar")]);
+        assert!(list.diagnostics.is_empty(), "{:?}", list.diagnostics);
+        assert_eq!(list.entries.len(), 1);
+        assert_eq!(list.entries[0].line, 4);
+    }
+
+    #[test]
+    fn rejects_unknown_category() {
+        let list = parse(&[&required("a.jar"), &format!("OPTIONAL | b.jar | {}", URL)]);
+        assert_eq!(list.errors().map(|d| d.line).collect::<Vec<_>>(), [2]);
+        assert!(errors(&list)[0].contains("unknown category `OPTIONAL`"), "{:?}", errors(&list));
+        assert_eq!(list.entries.len(), 1);
+
+        // Categories aren't case-sensitive
+        assert!(!parse(&[&format!("required | b.jar | {}", URL)]).has_errors());
+    }
+
+    #[test]
+    fn rejects_bad_urls() {
+        for url in ["not-a-url", "ftp://example.com/a.jar", ""] {
+            let list = parse(&[&format!("REQUIRED | a.jar | {}", url)]);
+            assert!(list.has_errors(), "{}", url);
+        }
+        let list = parse(&[&format!("REQUIRED | a.jar | {} not-a-mirror", URL)]);
+        assert!(errors(&list)[0].contains("invalid mirror URL"), "{:?}", errors(&list));
+
+        // REMOVE entries don't need a URL
+        assert!(!parse(&["REMOVE | a.jar | not-a-url"]).has_errors());
+    }
+
+    #[test]
+    fn rejects_malformed_hashes() {
+        let list = parse(&[&format!("REQUIRED | a.jar | {} | {}", URL, "a".repeat(63))]);
+        assert_eq!(list.errors().map(|d| d.line).collect::<Vec<_>>(), [1]);
+        assert!(errors(&list)[0].contains("expected 64 hexadecimal characters"), "{:?}", errors(&list));
+
+        let list = parse(&[&format!("REQUIRED | a.jar | {} | {}", URL, "g".repeat(64))]);
+        assert!(list.has_errors());
+
+        let list = parse(&[&format!("REQUIRED | a.jar | {} | {}", URL, "A".repeat(64))]);
+        assert_eq!(list.entries[0].hash.as_ref().unwrap().hex, "a".repeat(64));
+    }
+
+    #[test]
+    fn rejects_missing_columns() {
+        let list = parse(&["REQUIRED | a.jar"]);
+        assert!(errors(&list)[0].contains("found 2 column(s)"), "{:?}", errors(&list));
+
+        let list = parse(&[&format!("REQUIRED | a.jar | {} | | both | A | extra", URL)]);
+        assert!(!list.has_errors());
+        assert_eq!(list.warnings().count(), 1);
+    }
+}
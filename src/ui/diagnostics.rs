@@ -0,0 +1,83 @@
+// diagnostics.rs
+use eframe::{egui, App};
+use egui::{Color32, Label, ScrollArea};
+use crate::types::ListDiagnostic;
+
+/// Shown instead of syncing when the mod list could not be parsed cleanly
+pub struct DiagnosticsApp {
+    title: String,
+    diagnostics: Vec<ListDiagnostic>,
+}
+
+impl DiagnosticsApp {
+    pub fn new(title: impl Into<String>, diagnostics: Vec<ListDiagnostic>) -> Self {
+        Self {
+            title: title.into(),
+            diagnostics,
+        }
+    }
+
+    fn draw_diagnostics(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let button_height = 40.0;
+            let button_spacing = 20.0;
+
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label(
+                    egui::RichText::new(&self.title)
+                        .size(24.0)
+                        .color(Color32::from_rgb(0xFF, 0x00, 0x00))
+                );
+                ui.add_space(5.0);
+                ui.label(
+                    egui::RichText::new("No files were changed.")
+                        .color(Color32::from_gray(136))
+                );
+                ui.add_space(15.0);
+                ui.separator();
+                ui.add_space(5.0);
+
+                let list_height = ui.available_height() - button_height - button_spacing * 2.0;
+                ScrollArea::vertical()
+                    .max_height(list_height.max(40.0))
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        for diagnostic in &self.diagnostics {
+                            let color = if diagnostic.is_error() {
+                                Color32::from_rgb(0xFF, 0x00, 0x00)
+                            } else {
+                                Color32::from_rgb(0xFF, 0xFF, 0x00)
+                            };
+                            ui.horizontal(|ui| {
+                                ui.add_space(10.0);
+                                ui.add(Label::new(egui::RichText::new(diagnostic.to_string()).color(color)).truncate());
+                            });
+                        }
+                    });
+
+                ui.add_space(ui.available_height() - button_height - button_spacing);
+
+                let button = egui::Button::new(
+                    egui::RichText::new("Close")
+                        .size(18.0)
+                        .strong()
+                        .color(Color32::WHITE),
+                )
+                    .min_size(egui::vec2(180.0, button_height))
+                    .fill(Color32::from_rgb(0x10, 0x10, 0x10))
+                    .stroke(egui::Stroke::new(2.0, Color32::from_rgb(0xFF, 0x00, 0x00)));
+
+                if ui.add(button).clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+    }
+}
+
+impl App for DiagnosticsApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.draw_diagnostics(ctx);
+    }
+}
@@ -0,0 +1,70 @@
+// error.rs
+use eframe::{egui, App};
+use egui::{Color32, ScrollArea};
+
+/// Shown when ModSync has to stop, since in GUI mode there is no console to print the error to
+pub struct ErrorApp {
+    title: String,
+    message: String,
+}
+
+impl ErrorApp {
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+        }
+    }
+
+    fn draw_error(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let button_height = 40.0;
+            let button_spacing = 20.0;
+
+            ui.vertical_centered(|ui| {
+                ui.add_space(20.0);
+                ui.label(
+                    egui::RichText::new(&self.title)
+                        .size(24.0)
+                        .color(Color32::from_rgb(0xFF, 0x00, 0x00))
+                );
+                ui.add_space(15.0);
+                ui.separator();
+                ui.add_space(5.0);
+
+                let text_height = ui.available_height() - button_height - button_spacing * 2.0;
+                ScrollArea::vertical()
+                    .max_height(text_height.max(40.0))
+                    .show(ui, |ui| {
+                        ui.set_width(ui.available_width());
+                        ui.label(
+                            egui::RichText::new(&self.message)
+                                .color(Color32::from_rgb(0xF0, 0xF0, 0xF0))
+                        );
+                    });
+
+                ui.add_space(ui.available_height() - button_height - button_spacing);
+
+                let button = egui::Button::new(
+                    egui::RichText::new("Close")
+                        .size(18.0)
+                        .strong()
+                        .color(Color32::WHITE),
+                )
+                    .min_size(egui::vec2(180.0, button_height))
+                    .fill(Color32::from_rgb(0x10, 0x10, 0x10))
+                    .stroke(egui::Stroke::new(2.0, Color32::from_rgb(0xFF, 0x00, 0x00)));
+
+                if ui.add(button).clicked() {
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            });
+        });
+    }
+}
+
+impl App for ErrorApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.draw_error(ctx);
+    }
+}
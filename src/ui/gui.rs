@@ -3,10 +3,27 @@ use eframe::{egui, App};
 use std::sync::Arc;
 use std::time::Instant;
 use egui::{Direction, Vec2, ColorImage, TextureHandle};
-use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::mpsc::{error::TryRecvError, UnboundedReceiver};
 use crate::modmanager::{SyncProgress, SyncEvent, SyncReport};
 use crate::ui::theme::{setup_dark_theme, setup_fonts};
 
+/// Where the mod list came from, when that's worth telling the player
+pub enum ListNotice {
+    /// The list server couldn't be reached; holds when the saved list that is used instead was fetched
+    Offline(String),
+    /// The server confirmed the list didn't change since the last launch
+    Unchanged,
+}
+
+impl std::fmt::Display for ListNotice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListNotice::Offline(since) => write!(f, "Offline mode: using the mod list from {}", since),
+            ListNotice::Unchanged => f.write_str("Pack unchanged since last launch"),
+        }
+    }
+}
+
 pub struct ModSyncApp {
     progress: Arc<SyncProgress>,
     events: UnboundedReceiver<SyncEvent>,
@@ -19,7 +36,13 @@ pub struct ModSyncApp {
     // New state to track if we should launch transaction log window
     has_changes: bool,
     transaction_report: Option<SyncReport>,
-    show_transaction_log: bool,
+
+    // Latest retry notice (filename, message), cleared once that file is done
+    retry_status: Option<(String, String)>,
+    // Where the list came from, shown for the whole splash
+    list_notice: Option<ListNotice>,
+    // A dry run only plans; the plan window opens as soon as it's ready
+    dry_run: bool,
     report_sender: std::sync::mpsc::Sender<SyncReport>,
 
     // Logo image
@@ -33,6 +56,8 @@ impl ModSyncApp {
         events: UnboundedReceiver<SyncEvent>,
         timeout_secs: u64,
         report_sender: std::sync::mpsc::Sender<SyncReport>,
+        list_notice: Option<ListNotice>,
+        dry_run: bool,
     ) -> Self {
         setup_fonts(&cc.egui_ctx);
         setup_dark_theme(&cc.egui_ctx);
@@ -45,9 +70,11 @@ impl ModSyncApp {
             splash_finished: false,
             splash_start: None,
             splash_timeout_secs: timeout_secs as f32,
-            show_transaction_log: false,
             has_changes: false,
             transaction_report: None,
+            retry_status: None,
+            list_notice,
+            dry_run,
             logo_texture,
             report_sender, // Add this
         }
@@ -55,21 +82,49 @@ impl ModSyncApp {
 
     pub fn draw_splash(&mut self, ui: &mut egui::Ui) {
         // Drain events
-        while let Ok(event) = self.events.try_recv() {
+        loop {
+            let event = match self.events.try_recv() {
+                Ok(event) => event,
+                // The sync stopped without a report; main shows why once the splash is gone
+                Err(TryRecvError::Disconnected) if !self.splash_finished => {
+                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+                    break;
+                }
+                Err(_) => break,
+            };
             match event {
-                SyncEvent::Finished(report) => {
-                    if !self.splash_finished {
-                        self.splash_finished = true;
-                        self.splash_start = Some(Instant::now());
-
-                        // Store the report
-                        self.transaction_report = Some(report.clone());
-
-                        // Check if there were any changes
-                        self.has_changes = !report.downloaded.is_empty()
-                            || !report.removed.is_empty()
-                            || !report.failed.is_empty();
-                    }
+                SyncEvent::Finished(report) if report.dry_run => {
+                    self.splash_finished = true;
+                    let _ = self.report_sender.send(report);
+                    ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+                SyncEvent::Finished(report) if !self.splash_finished => {
+                    self.splash_finished = true;
+                    self.splash_start = Some(Instant::now());
+
+                    // Check if there were any changes
+                    self.has_changes = !report.downloaded.is_empty()
+                        || !report.updated.is_empty()
+                        || !report.removed.is_empty()
+                        || !report.failed.is_empty()
+                        || !report.exclusive_errors.is_empty();
+
+                    // Store the report
+                    self.transaction_report = Some(report);
+                }
+                SyncEvent::Retrying { filename, name, attempt, max_attempts, delay, .. } => {
+                    let text = format!(
+                        "Retrying {} in {}s (attempt {}/{})",
+                        name, delay.as_secs(), attempt, max_attempts
+                    );
+                    self.retry_status = Some((filename, text));
+                }
+                SyncEvent::Downloaded { filename, .. }
+                | SyncEvent::Updated { filename, .. }
+                | SyncEvent::Failed { filename, .. }
+                    if self.retry_status.as_ref().is_some_and(|(f, _)| *f == filename) =>
+                {
+                    self.retry_status = None;
                 }
                 // Handle other events if needed
                 _ => {}
@@ -104,7 +159,7 @@ impl ModSyncApp {
                 |ui| {
                     let stats = self.progress.stats();
 
-                    ui.columns(4, |columns| {
+                    ui.columns(6, |columns| {
                         for (i, column) in columns.iter_mut().enumerate() {
                             column.with_layout(
                                 Layout::top_down(egui::Align::Center)
@@ -116,19 +171,29 @@ impl ModSyncApp {
                                         0 => (
                                             stats.downloaded.to_string(),
                                             egui::Color32::from_rgb(0x00, 0xFF, 0x00),
-                                            "Downloaded",
+                                            if self.dry_run { "To download" } else { "Downloaded" },
                                         ),
                                         1 => (
+                                            stats.updated.to_string(),
+                                            egui::Color32::from_rgb(0x00, 0xBF, 0xFF),
+                                            if self.dry_run { "To update" } else { "Updated" },
+                                        ),
+                                        2 => (
                                             stats.unchanged.to_string(),
                                             egui::Color32::from_rgb(0xFF, 0xFF, 0x00),
                                             "Unchanged",
                                         ),
-                                        2 => (
+                                        3 => (
                                             stats.removed.to_string(),
                                             egui::Color32::from_rgb(0xFF, 0xA5, 0x00),
-                                            "Removed",
+                                            if self.dry_run { "To remove" } else { "Removed" },
                                         ),
-                                        3 => (
+                                        4 => (
+                                            stats.skipped.to_string(),
+                                            egui::Color32::from_rgb(0x88, 0x88, 0x88),
+                                            "Skipped",
+                                        ),
+                                        5 => (
                                             stats.failed.to_string(),
                                             egui::Color32::from_rgb(0xFF, 0x00, 0x00),
                                             "Failed",
@@ -153,16 +218,29 @@ impl ModSyncApp {
                 },
             );
 
+            if let Some(notice) = &self.list_notice {
+                let color = match notice {
+                    ListNotice::Offline(_) => egui::Color32::from_rgb(0xFF, 0xA5, 0x00),
+                    ListNotice::Unchanged => egui::Color32::from_rgb(0x88, 0x88, 0x88),
+                };
+                ui.label(egui::RichText::new(notice.to_string()).size(12.0).color(color));
+            }
+
             ui.add_space(10.0);
 
 
             // Handle different states
             if !self.splash_finished {
                 // Still processing
-                let total = self.progress.total as f32;
-                let done = self.progress.processed() as f32;
-                let fraction = if total > 0.0 { done / total } else { 0.0 };
-                let text = format!("Processed {}/{} mods", done as usize, total as usize);
+                let total = self.progress.total();
+                let done = self.progress.processed();
+                let stats = self.progress.stats();
+                let fraction = stats.fraction(done, total);
+                let verb = if self.dry_run { "Planned" } else { "Processed" };
+                let text = match stats.transfer_summary() {
+                    Some(transfer) => format!("{} {}/{} mods, {}", verb, done, total, transfer),
+                    None => format!("{} {}/{} mods", verb, done, total),
+                };
 
                 draw_squared_progress_bar(ui, fraction, &text, true);
 
@@ -175,17 +253,27 @@ impl ModSyncApp {
                     egui::RichText::new(last_mod)
                         .color(egui::Color32::from_rgb(0xF0, 0xF0, 0xF0)),
                 );
+
+                if let Some((_, retry_text)) = &self.retry_status {
+                    ui.label(
+                        egui::RichText::new(retry_text)
+                            .size(12.0)
+                            .color(egui::Color32::from_rgb(0xFF, 0xA5, 0x00)),
+                    );
+                }
             } else {
                 // Finished, show countdown or ready message
                 if self.has_changes {
                     // Check for click to open transaction log
-                    if self.splash_finished && self.has_changes && ui.input(|i| i.pointer.any_click()) {
-                        if let Some(report) = self.transaction_report.take() {
-                            // Send the report to main thread to open transaction log window
-                            let _ = self.report_sender.send(report);
-                            // Close this window
-                            ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
-                        }
+                    if self.splash_finished
+                        && self.has_changes
+                        && ui.input(|i| i.pointer.any_click())
+                        && let Some(report) = self.transaction_report.take()
+                    {
+                        // Send the report to main thread to open transaction log window
+                        let _ = self.report_sender.send(report);
+                        // Close this window
+                        ui.ctx().send_viewport_cmd(egui::ViewportCommand::Close);
                     }
 
                     // Show countdown bar for changes
@@ -330,7 +418,7 @@ fn load_logo(ctx: &egui::Context, target_width: u32, target_height: u32) -> Opti
 }
 
 impl App for ModSyncApp {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         egui::CentralPanel::default().show(ctx, |ui| {
             self.draw_splash(ui);
         });
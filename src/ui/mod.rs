@@ -1,5 +1,6 @@
+pub mod diagnostics;
+pub mod error;
 pub mod gui;
-pub mod state;
 pub mod theme;
 pub mod transaction_log;
 
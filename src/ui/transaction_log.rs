@@ -1,56 +1,137 @@
 // transaction_log.rs
 use eframe::{egui, App};
 use egui::{Color32, Label, Rect, ScrollArea, StrokeKind};
-use crate::modmanager::SyncReport;
+use std::path::PathBuf;
+use crate::modmanager::{format_bytes, transaction, SyncReport};
+use crate::types::{ListDiagnostic, ModEntry};
+
+/// Column title, symbol and color, in display order
+const COLUMNS: [(&str, &str, Color32); 6] = [
+    ("Downloaded", "+", Color32::from_rgb(0x00, 0xFF, 0x00)),
+    ("Updated", "*", Color32::from_rgb(0x00, 0xBF, 0xFF)),
+    ("Unchanged", "~", Color32::from_rgb(0xFF, 0xFF, 0x00)),
+    ("Removed", "-", Color32::from_rgb(0xFF, 0xA5, 0x00)),
+    ("Skipped", "/", Color32::from_rgb(0x88, 0x88, 0x88)),
+    ("Failed", "!", Color32::from_rgb(0xFF, 0x00, 0x00)),
+];
+
+/// Column titles when showing a --dry-run plan
+const PLAN_TITLES: [&str; COLUMNS.len()] = ["Download", "Update", "Unchanged", "Remove", "Skip", "Problems"];
+
+/// A single line in a transaction log column
+enum Row {
+    Folder(String),
+    Item(String),
+}
 
 pub struct TransactionLogApp {
     report: SyncReport,
-    scroll_offsets: [usize; 4], // track how many items to skip per column
+    warnings: Vec<ListDiagnostic>,
+    scroll_offsets: [usize; COLUMNS.len()], // track how many items to skip per column
     human_readable: bool,
+    mods_dir: PathBuf,
+    rollback_result: Option<Result<String, String>>, // message after the Rollback button was used
 }
 
 impl TransactionLogApp {
-    pub fn new(report: SyncReport) -> Self {
+    pub fn new(report: SyncReport, warnings: Vec<ListDiagnostic>, mods_dir: PathBuf) -> Self {
         Self {
             report,
-            scroll_offsets: [0; 4],
+            warnings,
+            scroll_offsets: [0; COLUMNS.len()],
             human_readable: true,
+            mods_dir,
+            rollback_result: None,
         }
     }
 
-    fn display_filename(&self, filename: &str) -> String {
-        if !self.human_readable {
-            filename.to_string()
+    /// Undoes the sync shown in the log
+    fn rollback(&mut self) {
+        let Some(id) = &self.report.transaction else {
+            return;
+        };
+        self.rollback_result = Some(match transaction::rollback(&self.mods_dir, Some(id)) {
+            Ok(summary) => Ok(format!(
+                "Rolled back: {} file(s) restored, {} file(s) deleted",
+                summary.restored, summary.deleted
+            )),
+            Err(e) => Err(format!("Rollback failed: {:#}", e)),
+        });
+    }
+
+    fn display_name(&self, entry: &ModEntry) -> String {
+        let name = if self.human_readable {
+            entry.display_name()
         } else {
-            let parts: Vec<&str> = filename.split('-').collect();
-            let mut result = String::new();
-
-            for (i, part) in parts.iter().enumerate() {
-                // Check if this part starts with a digit
-                if let Some(first_char) = part.chars().next() {
-                    if first_char.is_digit(10) {
-                        // Part starts with a digit, stop here
-                        break;
-                    }
-                }
+            entry.file_name().to_string()
+        };
+        // Plans show how much each transfer would be
+        let name = match entry.size {
+            Some(size) if self.report.dry_run => format!("{} ({})", name, format_bytes(size)),
+            _ => name,
+        };
+        // Files that only a mirror could deliver
+        let name = match self.report.sources.get(&entry.filename) {
+            Some(source) if *source != entry.url => format!("{} (via {})", name, mirror_host(source)),
+            _ => name,
+        };
+        match self.report.notes.get(&entry.filename) {
+            Some(note) => format!("{}: {}", name, note),
+            None => name,
+        }
+    }
+
+    /// Rows for every column, in the same order as COLUMNS.
+    /// Entries are grouped under folder headers once more than one target folder is involved.
+    fn column_items(&self) -> Vec<Vec<Row>> {
+        let report = &self.report;
+        let all_entries = report.downloaded.iter()
+            .chain(&report.updated)
+            .chain(&report.unchanged)
+            .chain(&report.removed)
+            .chain(&report.skipped)
+            .chain(report.failed.iter().map(|(e, _)| e));
+        let mut folders: Vec<&str> = all_entries.map(|e| e.target_dir()).collect();
+        folders.sort();
+        folders.dedup();
+        let grouped = folders.len() > 1;
+
+        let rows = |entries: Vec<(&ModEntry, String)>| {
+            if !grouped {
+                return entries.into_iter().map(|(_, text)| Row::Item(text)).collect();
+            }
 
-                // Add the part to result
-                if i > 0 {
-                    result.push('-');
+            let mut rows = Vec::new();
+            for folder in &folders {
+                let mut in_folder = entries.iter().filter(|(e, _)| e.target_dir() == *folder).peekable();
+                if in_folder.peek().is_some() {
+                    rows.push(Row::Folder(format!("{}/", folder)));
+                    rows.extend(in_folder.map(|(_, text)| Row::Item(text.clone())));
                 }
-                result.push_str(part);
             }
+            rows
+        };
+        let names = |entries: &[ModEntry]| {
+            rows(entries.iter().map(|e| (e, self.display_name(e))).collect())
+        };
 
-            result
-        }
+        vec![
+            names(&report.downloaded),
+            names(&report.updated),
+            names(&report.unchanged),
+            names(&report.removed),
+            names(&report.skipped),
+            rows(report.failed
+                .iter()
+                .map(|(e, error)| (e, format!("{}: {}", self.display_name(e), error)))
+                .collect()),
+        ]
     }
 
     fn draw_transaction_log(&mut self, ctx: &egui::Context) {
         egui::CentralPanel::default().show(ctx, |ui| {
             let mut column_rects: Vec<(Rect, Color32)> = vec![];
 
-            let hrn_height = 10.0;
-            let hrn_spacing = 10.0;
             let button_height = 40.0;
             let button_spacing = 20.0;
             let header_height = 80.0;
@@ -58,36 +139,34 @@ impl TransactionLogApp {
             ui.vertical_centered(|ui| {
                 ui.add_space(20.0);
                 ui.label(
-                    egui::RichText::new("Transaction Log")
+                    egui::RichText::new(if self.report.dry_run { "Plan (nothing was changed)" } else { "Transaction Log" })
                         .size(24.0)
                         .color(Color32::from_rgb(0x2F, 0x36, 0x99))
                 );
                 ui.add_space(25.0);
 
-                let available_width = ui.available_width();
                 let available_height = ui.available_height() - button_height - button_spacing * 2.0 - header_height;
                 let column_height = available_height.max(80.0);
-                let column_width = available_width / 4.0;
                 let item_height = 20.0;
 
-                // Create 4 columns for the transaction log
-                ui.columns(4, |columns| {
-                    let categories = ["Downloaded", "Unchanged", "Removed", "Failed"];
-                    let symbols = ["+", "~", "-", "!"];
-                    let colors = [
-                        Color32::from_rgb(0x00, 0xFF, 0x00),
-                        Color32::from_rgb(0xFF, 0xFF, 0x00),
-                        Color32::from_rgb(0xFF, 0xA5, 0x00),
-                        Color32::from_rgb(0xFF, 0x00, 0x00),
-                    ];
+                let column_items = self.column_items();
+                let column_count = COLUMNS.len();
 
+                // Create one column per result category
+                ui.columns(column_count, |columns| {
                     for (i, column) in columns.iter_mut().enumerate() {
+                        let (mut category, symbol, color) = COLUMNS[i];
+                        if self.report.dry_run {
+                            category = PLAN_TITLES[i];
+                        }
+                        let items = &column_items[i];
+
                         column.vertical(|ui| {
                             ui.add_space(15.0);
                             ui.vertical_centered(|ui| {
                                 ui.label(
-                                    egui::RichText::new(categories[i])
-                                        .color(colors[i])
+                                    egui::RichText::new(category)
+                                        .color(color)
                                         .size(16.0),
                                 );
                             });
@@ -96,13 +175,7 @@ impl TransactionLogApp {
                             ui.add_space(5.0);
 
                             // Total items and how many fit
-                            let total_items = match i {
-                                0 => self.report.downloaded.len(),
-                                1 => self.report.unchanged.len(),
-                                2 => self.report.removed.len(),
-                                3 => self.report.failed.len(),
-                                _ => 0,
-                            };
+                            let total_items = items.len();
                             let items_fit = (column_height / item_height).floor() as usize;
 
                             // Auto scroll back if more space is available
@@ -134,152 +207,63 @@ impl TransactionLogApp {
                                 .show(ui, |ui| {
                                     ui.set_width(ui.available_width());
                                     // Draw visible items
-                                    match i {
-                                        0 => {
-                                            if hide_top {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            for entry in self.report.downloaded.iter().skip(visible_range_start).take(visible_range_end - visible_range_start) {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new(symbols[i]).color(colors[i])).truncate());
-                                                    ui.add_space(5.0);
-                                                    ui.add(Label::new(self.display_filename(&*entry.filename)).truncate());
-                                                });
-                                            }
-                                            if hide_bottom {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            if self.report.downloaded.is_empty() {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("None").color(Color32::from_gray(136)).size(12.0)).truncate());
-                                                });
-                                            }
-                                        }
-                                        1 => {
-                                            if hide_top {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            for entry in self.report.unchanged.iter().skip(visible_range_start).take(visible_range_end - visible_range_start) {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new(symbols[i]).color(colors[i])).truncate());
-                                                    ui.add_space(5.0);
-                                                    ui.add(Label::new(self.display_filename(&*entry.filename)).truncate());
-                                                });
-                                            }
-                                            if hide_bottom {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            if self.report.unchanged.is_empty() {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("None").color(Color32::from_gray(136)).size(12.0)).truncate());
-                                                });
-                                            }
-                                        }
-                                        2 => {
-                                            if hide_top {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            for entry in self.report.removed.iter().skip(visible_range_start).take(visible_range_end - visible_range_start) {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new(symbols[i]).color(colors[i])).truncate());
-                                                    ui.add_space(5.0);
-                                                    ui.add(Label::new(self.display_filename(&*entry.filename)).truncate());
-                                                });
-                                            }
-                                            if hide_bottom {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            if self.report.removed.is_empty() {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("None").color(Color32::from_gray(136)).size(12.0)).truncate());
-                                                });
-                                            }
-                                        }
-                                        3 => {
-                                            if hide_top {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            for (entry, error) in self.report.failed.iter().skip(visible_range_start).take(visible_range_end - visible_range_start) {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new(symbols[i]).color(colors[i])).truncate());
+                                    if hide_top {
+                                        ui.horizontal(|ui| {
+                                            ui.add_space(10.0);
+                                            ui.add(Label::new(egui::RichText::new("...").color(color)).truncate());
+                                        });
+                                    }
+                                    for row in items.iter().skip(visible_range_start).take(visible_range_end - visible_range_start) {
+                                        ui.horizontal(|ui| {
+                                            ui.add_space(10.0);
+                                            match row {
+                                                Row::Folder(folder) => {
+                                                    ui.add(Label::new(egui::RichText::new(folder).color(Color32::from_gray(136))).truncate());
+                                                }
+                                                Row::Item(item) => {
+                                                    ui.add(Label::new(egui::RichText::new(symbol).color(color)).truncate());
                                                     ui.add_space(5.0);
-                                                    ui.add(Label::new(format!("{}: {}", self.display_filename(&*entry.filename), error)).truncate());
-                                                });
+                                                    ui.add(Label::new(item).truncate());
+                                                }
                                             }
-                                            if hide_bottom {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("...").color(colors[i])).truncate());
-                                                });
-                                            }
-                                            if self.report.failed.is_empty() {
-                                                ui.horizontal(|ui| {
-                                                    ui.add_space(10.0);
-                                                    ui.add(Label::new(egui::RichText::new("None").color(Color32::from_gray(136)).size(12.0)).truncate());
-                                                });
-                                            }
-                                        }
-                                        _ => unreachable!(),
+                                        });
+                                    }
+                                    if hide_bottom {
+                                        ui.horizontal(|ui| {
+                                            ui.add_space(10.0);
+                                            ui.add(Label::new(egui::RichText::new("...").color(color)).truncate());
+                                        });
+                                    }
+                                    if items.is_empty() {
+                                        ui.horizontal(|ui| {
+                                            ui.add_space(10.0);
+                                            ui.add(Label::new(egui::RichText::new("None").color(Color32::from_gray(136)).size(12.0)).truncate());
+                                        });
                                     }
                                 });
                         });
-                        column_rects.push((column.min_rect(), colors[i]));
+                        column_rects.push((column.min_rect(), color));
                     }
                 });
 
                 // Determine which column the scroll was meant for
                 let scroll_delta = ui.input(|i| i.raw_scroll_delta.y);
-                if scroll_delta != 0.0 {
-                    if let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
-                        for (i, (rect, _color)) in column_rects.iter().enumerate() {
-                            if rect.contains(pos) {
-                                // Total items and how many fit in this column
-                                let total_items = match i {
-                                    0 => self.report.downloaded.len(),
-                                    1 => self.report.unchanged.len(),
-                                    2 => self.report.removed.len(),
-                                    3 => self.report.failed.len(),
-                                    _ => 0,
-                                };
-                                let items_fit = (column_height / item_height).floor() as usize;
+                if scroll_delta != 0.0
+                    && let Some(pos) = ui.input(|i| i.pointer.hover_pos()) {
+                    for (i, (rect, _color)) in column_rects.iter().enumerate() {
+                        if rect.contains(pos) {
+                            // Total items and how many fit in this column
+                            let total_items = column_items[i].len();
+                            let items_fit = (column_height / item_height).floor() as usize;
 
-                                if total_items > items_fit {
-                                    if scroll_delta < 0.0 {
-                                        self.scroll_offsets[i] = (self.scroll_offsets[i] + 1).min(total_items - items_fit);
-                                    } else if scroll_delta > 0.0 {
-                                        self.scroll_offsets[i] = self.scroll_offsets[i].saturating_sub(1);
-                                    }
+                            if total_items > items_fit {
+                                if scroll_delta < 0.0 {
+                                    self.scroll_offsets[i] = (self.scroll_offsets[i] + 1).min(total_items - items_fit);
+                                } else if scroll_delta > 0.0 {
+                                    self.scroll_offsets[i] = self.scroll_offsets[i].saturating_sub(1);
                                 }
-                                break; // Only scroll one column
                             }
+                            break; // Only scroll one column
                         }
                     }
                 }
@@ -299,17 +283,66 @@ impl TransactionLogApp {
                     self.human_readable = !self.human_readable;
                 }
 
+                if !self.warnings.is_empty() {
+                    let tooltip = self.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("\n");
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(format!("{} mod list warning(s)", self.warnings.len()))
+                            .color(Color32::from_rgb(0xFF, 0xFF, 0x00)),
+                    ).on_hover_text(tooltip);
+                }
+
+                if !self.report.exclusive_errors.is_empty() {
+                    ui.add_space(5.0);
+                    ui.label(
+                        egui::RichText::new(format!("{} exclusive mode problem(s)", self.report.exclusive_errors.len()))
+                            .color(Color32::from_rgb(0xFF, 0x00, 0x00)),
+                    ).on_hover_text(self.report.exclusive_errors.join("\n"));
+                }
+
+                if let Some(result) = &self.rollback_result {
+                    let (text, color) = match result {
+                        Ok(text) => (text, Color32::from_rgb(0x00, 0xFF, 0x00)),
+                        Err(text) => (text, Color32::from_rgb(0xFF, 0x00, 0x00)),
+                    };
+                    ui.add_space(5.0);
+                    ui.label(egui::RichText::new(text).color(color));
+                }
+
                 ui.add_space(10.0);
 
+                // Rollback is offered until it was used once
+                let can_roll_back = self.report.transaction.is_some() && self.rollback_result.is_none();
+                let button_width = 180.0;
+                let buttons_width = if can_roll_back { button_width * 2.0 + button_spacing } else { button_width };
+
+                let button_response = ui.horizontal(|ui| {
+                    ui.add_space(((ui.available_width() - buttons_width) / 2.0).max(0.0));
+
+                    if can_roll_back {
+                        let rollback_button = egui::Button::new(
+                            egui::RichText::new("Rollback")
+                                .size(18.0)
+                                .strong()
+                                .color(Color32::WHITE),
+                        )
+                            .min_size(egui::vec2(button_width, button_height))
+                            .fill(Color32::from_rgb(0x10, 0x10, 0x10))
+                            .stroke(egui::Stroke::new(2.0, Color32::from_rgb(0xFF, 0xA5, 0x00)));
+
+                        if ui.add(rollback_button).on_hover_text("Undo every change of this sync").clicked() {
+                            self.rollback();
+                        }
+                        ui.add_space(button_spacing);
+                    }
 
-                let button_response = ui.vertical_centered(|ui| {
                     let button = egui::Button::new(
-                        egui::RichText::new("Launch Game")
+                        egui::RichText::new(if self.report.dry_run { "Close" } else { "Launch Game" })
                             .size(18.0)
                             .strong()
                             .color(Color32::WHITE),
                     )
-                        .min_size(egui::vec2(180.0, button_height))
+                        .min_size(egui::vec2(button_width, button_height))
                         .fill(Color32::from_rgb(0x10, 0x10, 0x10))
                         .stroke(egui::Stroke::new(2.0, Color32::from_rgb(0x00, 0xFF, 0x00)));
 
@@ -321,9 +354,8 @@ impl TransactionLogApp {
                 });
 
                 // Draw outlines and separators after content
-                for (i, r) in column_rects.iter().enumerate() {
-                    let (column_rect, color) = r;
-                    let mut rect = column_rect.clone();
+                for (column_rect, color) in &column_rects {
+                    let mut rect = *column_rect;
                     rect.set_bottom(button_response.response.rect.top() - button_spacing * 2.0);
 
                     let stroke = egui::Stroke::new(1.5, *color);
@@ -341,3 +373,11 @@ impl App for TransactionLogApp {
         self.draw_transaction_log(ctx);
     }
 }
+
+/// Host part of a mirror URL, which is all that fits into a column
+fn mirror_host(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}